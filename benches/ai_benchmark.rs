@@ -2,6 +2,7 @@ use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criteri
 use jungo::ai::RandomAI;
 use jungo::board::{Board, Stone};
 use jungo::player::Player;
+use jungo::view::GameView;
 
 fn bench_ai_move_selection(c: &mut Criterion) {
     let mut group = c.benchmark_group("ai_move_selection");
@@ -30,9 +31,9 @@ fn bench_ai_move_selection(c: &mut Criterion) {
             BenchmarkId::new("RandomAI", format!("{}x{}", size, size)),
             &board,
             |b, board| {
-                let ai = RandomAI::new();
+                let mut ai = RandomAI::new();
                 b.iter(|| {
-                    black_box(ai.get_move(board, Stone::Black));
+                    black_box(ai.get_move(&GameView::new(board, Stone::Black)));
                 });
             },
         );
@@ -55,9 +56,9 @@ fn bench_ai_empty_board(c: &mut Criterion) {
             BenchmarkId::new("RandomAI", format!("{}x{}", size, size)),
             &board,
             |b, board| {
-                let ai = RandomAI::new();
+                let mut ai = RandomAI::new();
                 b.iter(|| {
-                    black_box(ai.get_move(board, Stone::Black));
+                    black_box(ai.get_move(&GameView::new(board, Stone::Black)));
                 });
             },
         );
@@ -89,9 +90,9 @@ fn bench_ai_dense_board(c: &mut Criterion) {
     }
 
     group.bench_function("RandomAI_19x19_dense", |b| {
-        let ai = RandomAI::new();
+        let mut ai = RandomAI::new();
         b.iter(|| {
-            black_box(ai.get_move(&board, Stone::Black));
+            black_box(ai.get_move(&GameView::new(&board, Stone::Black)));
         });
     });
 
@@ -101,7 +102,7 @@ fn bench_ai_dense_board(c: &mut Criterion) {
 }
 
 fn bench_mcts_playout(c: &mut Criterion) {
-    use jungo::ai::{Mcts, MonteCarloAI};
+    use jungo::ai::{Mcts, MonteCarloAI, SearchBudget};
 
     let mut group = c.benchmark_group("mcts_operations");
     group.sample_size(10); // MCTS is slower, so reduce sample size
@@ -111,26 +112,70 @@ fn bench_mcts_playout(c: &mut Criterion) {
 
     group.bench_function("MCTS_get_move_9x9_10sims", |b| {
         b.iter(|| {
-            let mcts = Mcts::new(10); // Only 10 simulations for benchmarking
-            black_box(mcts.get_move(&board, Stone::Black));
+            let mut mcts = Mcts::new_with_budget(SearchBudget::Playouts(10)).with_seed(1);
+            black_box(mcts.get_move(&GameView::new(&board, Stone::Black)));
         });
     });
 
     group.bench_function("MonteCarloAI_get_move_9x9_10sims", |b| {
         b.iter(|| {
-            let mc = MonteCarloAI::new(10); // Only 10 simulations for benchmarking
-            black_box(mc.get_move(&board, Stone::Black));
+            let mut mc = MonteCarloAI::new_with_budget(SearchBudget::Playouts(10)).with_seed(1);
+            black_box(mc.get_move(&GameView::new(&board, Stone::Black)));
         });
     });
 
     group.finish();
 }
 
+// Playouts per second of a fixed-time MCTS search as threads are added.
+// Each measured element is one playout.
+fn bench_mcts_scaling(c: &mut Criterion) {
+    use criterion::Throughput;
+    use jungo::ai::{Mcts, Parallelism};
+    use std::time::{Duration, Instant};
+
+    let mut group = c.benchmark_group("mcts_scaling");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(1));
+
+    let board = Board::new(9);
+    let max_threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let thread_counts = [1, 2, 4, 8, 16]
+        .into_iter()
+        .filter(|&threads| threads == 1 || threads <= max_threads);
+
+    for threads in thread_counts {
+        for (label, parallelism) in [("tree", Parallelism::Tree), ("root", Parallelism::Root)] {
+            group.bench_with_input(BenchmarkId::new(label, threads), &threads, |b, &threads| {
+                b.iter_custom(|playouts| {
+                    let mut elapsed = Duration::ZERO;
+                    let mut done = 0;
+                    while done < playouts {
+                        // A fresh player each time so the tree does not keep growing
+                        let mut mcts = Mcts::new_with_millis(100)
+                            .with_threads(threads)
+                            .with_parallelism(parallelism);
+                        let start = Instant::now();
+                        black_box(mcts.get_move(&GameView::new(&board, Stone::Black)));
+                        elapsed += start.elapsed();
+                        done += mcts.last_playouts().max(1);
+                    }
+                    // Time for exactly `playouts` playouts at the measured rate
+                    elapsed.mul_f64(playouts as f64 / done as f64)
+                });
+            });
+        }
+    }
+
+    group.finish();
+}
+
 criterion_group!(
     benches,
     bench_ai_move_selection,
     bench_ai_empty_board,
     bench_ai_dense_board,
-    bench_mcts_playout
+    bench_mcts_playout,
+    bench_mcts_scaling
 );
 criterion_main!(benches);
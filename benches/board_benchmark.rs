@@ -104,9 +104,42 @@ fn bench_is_eye(c: &mut Criterion) {
     group.finish();
 }
 
+fn bench_board_clone(c: &mut Criterion) {
+    use jungo::zobrist::ZobristTable;
+
+    let mut group = c.benchmark_group("clone");
+
+    for size in [9, 13, 19].iter() {
+        let mut board = Board::new(*size);
+        let mut stone = Stone::Black;
+        for i in 0..(size * size / 3) {
+            let _ = board.place_stone((i * 7) % size, (i * 11) % size, stone);
+            stone = stone.opposite();
+        }
+
+        group.bench_with_input(BenchmarkId::new("Board", size), &board, |b, board| {
+            b.iter(|| black_box(board.clone()));
+        });
+
+        // Boards used to embed their own Zobrist table, so every clone also
+        // paid for a deep copy of it. This is that extra cost.
+        let table = ZobristTable::new(*size);
+        group.bench_with_input(
+            BenchmarkId::new("ZobristTable", size),
+            &table,
+            |b, table| {
+                b.iter(|| black_box(table.clone()));
+            },
+        );
+    }
+
+    group.finish();
+}
+
 fn bench_full_game_playout(c: &mut Criterion) {
     use jungo::ai::RandomAI;
     use jungo::player::Player;
+    use jungo::view::GameView;
 
     let mut group = c.benchmark_group("full_game_playout");
     group.sample_size(20); // Reduce sample size for longer benchmarks
@@ -114,18 +147,18 @@ fn bench_full_game_playout(c: &mut Criterion) {
     group.bench_function("RandomAI_9x9", |b| {
         b.iter(|| {
             let mut board = Board::new(9);
-            let ai1 = RandomAI::new();
-            let ai2 = RandomAI::new();
+            let mut ai1 = RandomAI::new();
+            let mut ai2 = RandomAI::new();
             let mut current_turn = Stone::Black;
             let mut consecutive_passes = 0;
 
             loop {
-                let ai: &dyn Player = match current_turn {
-                    Stone::Black => &ai1,
-                    Stone::White => &ai2,
+                let ai: &mut dyn Player = match current_turn {
+                    Stone::Black => &mut ai1,
+                    Stone::White => &mut ai2,
                 };
 
-                match ai.get_move(&board, current_turn) {
+                match ai.get_move(&GameView::new(&board, current_turn)) {
                     Some((x, y)) => {
                         if board.place_stone(x, y, current_turn).is_ok() {
                             consecutive_passes = 0;
@@ -153,6 +186,7 @@ criterion_group!(
     bench_place_stone,
     bench_get_operation,
     bench_is_eye,
+    bench_board_clone,
     bench_full_game_playout
 );
 criterion_main!(benches);
@@ -2,6 +2,7 @@ use criterion::{black_box, criterion_group, criterion_main, Criterion};
 use jungo::ai::RandomAI;
 use jungo::board::{Board, Stone};
 use jungo::player::Player;
+use jungo::view::GameView;
 
 fn benchmark_random_ai_empty_board(c: &mut Criterion) {
     let mut group = c.benchmark_group("random_ai_empty");
@@ -9,8 +10,8 @@ fn benchmark_random_ai_empty_board(c: &mut Criterion) {
     for size in [5, 9, 19].iter() {
         group.bench_with_input(format!("{}x{}", size, size), size, |b, &size| {
             let board = Board::new(size);
-            let ai = RandomAI::new();
-            b.iter(|| black_box(ai.get_move(&board, Stone::Black)));
+            let mut ai = RandomAI::new();
+            b.iter(|| black_box(ai.get_move(&GameView::new(&board, Stone::Black))));
         });
     }
 
@@ -24,7 +25,7 @@ fn benchmark_random_ai_midgame(c: &mut Criterion) {
         group.bench_with_input(format!("{}x{}", size, size), size, |b, &size| {
             let mut board = Board::new(size);
             // Fill about 40% of the board
-            let positions_to_fill = (size * size * 2 / 5) as usize;
+            let positions_to_fill = size * size * 2 / 5;
             let mut filled = 0;
             let mut stone = Stone::Black;
 
@@ -43,8 +44,8 @@ fn benchmark_random_ai_midgame(c: &mut Criterion) {
                 }
             }
 
-            let ai = RandomAI::new();
-            b.iter(|| black_box(ai.get_move(&board, Stone::Black)));
+            let mut ai = RandomAI::new();
+            b.iter(|| black_box(ai.get_move(&GameView::new(&board, Stone::Black))));
         });
     }
 
@@ -70,8 +71,8 @@ fn benchmark_random_ai_endgame(c: &mut Criterion) {
             }
         }
 
-        let ai = RandomAI::new();
-        b.iter(|| black_box(ai.get_move(&board, Stone::Black)));
+        let mut ai = RandomAI::new();
+        b.iter(|| black_box(ai.get_move(&GameView::new(&board, Stone::Black))));
     });
 
     group.finish();
@@ -0,0 +1,95 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+// How long a search may run. Iteration and playout budgets do not depend
+// on machine speed, so with a fixed seed they always pick the same move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchBudget {
+    Time(Duration),
+    // Rounds of the search loop: one descent of the tree for Mcts, as many
+    // playouts as there are candidate moves for MonteCarloAI
+    Iterations(u64),
+    // Simulated games played out to the end
+    Playouts(u64),
+}
+
+impl SearchBudget {
+    pub fn from_secs(secs: u64) -> Self {
+        SearchBudget::Time(Duration::from_secs(secs))
+    }
+
+    pub fn from_millis(millis: u64) -> Self {
+        SearchBudget::Time(Duration::from_millis(millis))
+    }
+}
+
+impl fmt::Display for SearchBudget {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SearchBudget::Time(time) if time.subsec_nanos() == 0 => {
+                write!(f, "{}s", time.as_secs())
+            }
+            SearchBudget::Time(time) => write!(f, "{:.1}s", time.as_secs_f64()),
+            SearchBudget::Iterations(n) => write!(f, "{} iterations", n),
+            SearchBudget::Playouts(n) => write!(f, "{} playouts", n),
+        }
+    }
+}
+
+// What is left of a budget while a search runs
+#[derive(Debug, Clone)]
+pub(crate) struct Countdown {
+    deadline: Option<Instant>,
+    iterations: u64,
+    playouts: u64,
+}
+
+impl Countdown {
+    pub(crate) fn start(budget: SearchBudget) -> Self {
+        let mut countdown = Countdown {
+            deadline: None,
+            iterations: u64::MAX,
+            playouts: u64::MAX,
+        };
+        match budget {
+            // A time too long for an Instant to reach never runs out
+            SearchBudget::Time(time) => countdown.deadline = Instant::now().checked_add(time),
+            SearchBudget::Iterations(n) => countdown.iterations = n,
+            SearchBudget::Playouts(n) => countdown.playouts = n,
+        }
+        countdown
+    }
+
+    pub(crate) fn is_over(&self) -> bool {
+        self.iterations == 0
+            || self.playouts == 0
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    pub(crate) fn iteration_done(&mut self) {
+        self.iterations = self.iterations.saturating_sub(1);
+    }
+
+    pub(crate) fn playout_done(&mut self) {
+        self.playouts = self.playouts.saturating_sub(1);
+    }
+
+    // Shares the remaining iterations and playouts out as evenly as
+    // possible over `parts` searches; all of them keep the same deadline
+    pub(crate) fn split(&self, parts: usize) -> Vec<Countdown> {
+        let parts = parts as u64;
+        let share = |total: u64, part: u64| match total {
+            u64::MAX => u64::MAX,
+            _ => total / parts + u64::from(part < total % parts),
+        };
+        (0..parts)
+            .map(|part| Countdown {
+                deadline: self.deadline,
+                iterations: share(self.iterations, part),
+                playouts: share(self.playouts, part),
+            })
+            .collect()
+    }
+}
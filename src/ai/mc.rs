@@ -1,120 +1,131 @@
-use crate::ai::RandomAI;
+use crate::ai::budget::{Countdown, SearchBudget};
+use crate::ai::playout::{PlayoutPolicy, RandomPolicy};
+use crate::analysis::{Analysis, CandidateInfo, Reporter};
 use crate::board::{Board, Stone};
+use crate::game::Move;
 use crate::player::Player;
+use crate::view::GameView;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
-pub struct MonteCarloAI {
-    name: String,
-    time_limit: Duration,
+// How the simulations of a search are shared out over the candidate moves
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum MoveAllocation {
+    // Every move in turn, picking the best win rate in the end
+    #[default]
+    RoundRobin,
+    // The move with the highest upper confidence bound, exploration
+    // constant given (sqrt(2) in the textbook version)
+    Ucb1(f64),
+    // Rounds giving every remaining move the same number of simulations,
+    // twice as many as the round before, after which the worse half of
+    // the moves is dropped. Once one move is left all moves come back for
+    // another cycle starting from twice the simulations of the last one,
+    // so the search does not depend on knowing its budget.
+    SuccessiveHalving,
 }
 
-impl MonteCarloAI {
-    pub fn new(time_seconds: u64) -> Self {
-        MonteCarloAI {
-            name: format!("Monte Carlo AI ({}s)", time_seconds),
-            time_limit: Duration::from_secs(time_seconds),
+impl fmt::Display for MoveAllocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoveAllocation::RoundRobin => write!(f, "round-robin"),
+            MoveAllocation::Ucb1(_) => write!(f, "UCB1"),
+            MoveAllocation::SuccessiveHalving => write!(f, "successive halving"),
         }
     }
+}
 
-    pub fn new_with_millis(time_millis: u64) -> Self {
-        MonteCarloAI {
-            name: format!("Monte Carlo AI ({:.1}s)", time_millis as f64 / 1000.0),
-            time_limit: Duration::from_millis(time_millis),
+impl FromStr for MoveAllocation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "round-robin" | "rr" => Ok(MoveAllocation::RoundRobin),
+            "ucb1" | "ucb" => Ok(MoveAllocation::Ucb1(std::f64::consts::SQRT_2)),
+            "successive-halving" | "halving" => Ok(MoveAllocation::SuccessiveHalving),
+            _ => Err(format!("unknown move allocation '{}'", s)),
         }
     }
+}
 
-    fn simulate_game(&self, board: &Board, stone: Stone, first_move: (usize, usize)) -> f64 {
-        // Create a new board with the same state including captured stones
-        let mut sim_board = board.clone();
+pub struct MonteCarloAI {
+    name: String,
+    budget: SearchBudget,
+    rng: StdRng,
+    policy: Arc<dyn PlayoutPolicy>,
+    allocation: MoveAllocation,
+}
 
-        // Apply the first move
-        if sim_board
-            .place_stone(first_move.0, first_move.1, stone)
-            .is_err()
-        {
-            // Invalid move, return loss
-            return 0.0;
-        }
+impl MonteCarloAI {
+    pub fn new(time_seconds: u64) -> Self {
+        Self::new_with_budget(SearchBudget::from_secs(time_seconds))
+    }
 
-        let mut current_turn = stone.opposite();
-        let mut consecutive_passes = 0;
+    pub fn new_with_millis(time_millis: u64) -> Self {
+        Self::new_with_budget(SearchBudget::from_millis(time_millis))
+    }
 
-        // Create two RandomAI players
-        let random1 = RandomAI::new();
-        let random2 = RandomAI::new();
+    pub fn new_with_budget(budget: SearchBudget) -> Self {
+        let mut mc = MonteCarloAI {
+            name: String::new(),
+            budget,
+            rng: StdRng::from_entropy(),
+            policy: Arc::new(RandomPolicy),
+            allocation: MoveAllocation::default(),
+        };
+        mc.update_name();
+        mc
+    }
 
-        // Play out the game with a maximum number of moves to prevent long games
-        let mut moves = 0;
-        let max_moves = board.size() * board.size() * 2;
+    // Makes the playouts reproducible, so an iteration or playout budget
+    // always leads to the same move
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
 
-        loop {
-            let current_player: &dyn Player = match current_turn {
-                s if s == stone => &random1,
-                _ => &random2,
-            };
+    // Plays the simulations with `policy` instead of RandomAI's moves
+    pub fn with_playout_policy(mut self, policy: impl PlayoutPolicy + 'static) -> Self {
+        self.policy = Arc::new(policy);
+        self.update_name();
+        self
+    }
 
-            match current_player.get_move(&sim_board, current_turn) {
-                Some((x, y)) => {
-                    // In simulation, we don't track Ko rule for performance
-                    if sim_board.place_stone(x, y, current_turn).is_ok() {
-                        consecutive_passes = 0;
-                    }
-                }
-                None => {
-                    consecutive_passes += 1;
-                    if consecutive_passes >= 2 {
-                        break;
-                    }
-                }
-            }
+    pub fn with_allocation(mut self, allocation: MoveAllocation) -> Self {
+        self.allocation = allocation;
+        self.update_name();
+        self
+    }
 
-            current_turn = current_turn.opposite();
+    // The strategy sharing out the simulations, also shown in the name
+    pub fn allocation(&self) -> MoveAllocation {
+        self.allocation
+    }
 
-            moves += 1;
-            if moves >= max_moves {
-                break;
-            }
+    fn update_name(&mut self) {
+        let mut details = self.budget.to_string();
+        if self.allocation != MoveAllocation::RoundRobin {
+            details += &format!(", {}", self.allocation);
         }
-
-        // Evaluate final position
-        let (black_stones, white_stones) = sim_board.count_stones();
-        let (black_captured, white_captured) = sim_board.get_captured();
-
-        let black_score = (black_stones + black_captured) as i32;
-        let white_score = (white_stones + white_captured) as i32;
-
-        // Return win (1.0) or loss (0.0) from perspective of the original stone
-        match stone {
-            Stone::Black => {
-                if black_score > white_score {
-                    1.0
-                } else {
-                    0.0
-                }
-            }
-            Stone::White => {
-                if white_score > black_score {
-                    1.0
-                } else {
-                    0.0
-                }
-            }
+        if self.policy.name() != RandomPolicy.name() {
+            details += &format!(", {} playouts", self.policy.name());
         }
-    }
-}
-
-impl Player for MonteCarloAI {
-    fn name(&self) -> &str {
-        &self.name
+        self.name = format!("Monte Carlo AI ({})", details);
     }
 
-    fn get_move(&self, board: &Board, stone: Stone) -> Option<(usize, usize)> {
+    fn search(&mut self, view: &GameView, mut report: Option<Reporter>) -> Analysis {
+        let (board, stone) = (view.board, view.to_move);
+        let started = Instant::now();
         let mut valid_moves = Vec::new();
         let mut non_eye_moves = Vec::new();
 
         for y in 0..board.size() {
             for x in 0..board.size() {
-                if board.is_valid_move(x, y, stone) {
+                if view.is_legal(Move::Place(x, y)) {
                     valid_moves.push((x, y));
                     if !board.is_eye(x, y, stone) {
                         non_eye_moves.push((x, y));
@@ -131,61 +142,288 @@ impl Player for MonteCarloAI {
             valid_moves = non_eye_moves;
         } else if total_eyes <= 2 && non_eye_moves.is_empty() {
             // Only eye moves available and we have 2 or fewer eyes, pass
-            return None;
-        }
-
-        if valid_moves.is_empty() {
-            return None;
+            valid_moves.clear();
         }
 
         // Run simulations for each valid move
         let mut move_wins = vec![0; valid_moves.len()];
         let mut move_games = vec![0; valid_moves.len()];
-        let mut _total_simulations = 0;
+        let mut next_report = report
+            .as_ref()
+            .and_then(|(interval, _)| started.checked_add(*interval));
 
-        let start_time = Instant::now();
+        let mut countdown = Countdown::start(self.budget);
+        let mut allocator = Allocator::new(self.allocation, valid_moves.len());
+        let allocation = self.allocation;
 
-        // Run simulations until time limit
-        while start_time.elapsed() < self.time_limit {
-            for (idx, &(x, y)) in valid_moves.iter().enumerate() {
-                if start_time.elapsed() >= self.time_limit {
-                    break;
+        // Run simulations until the budget is used up. An iteration is as
+        // many simulations as there are moves, however they are allocated.
+        let mut simulations = 0;
+        while !valid_moves.is_empty() && !countdown.is_over() {
+            let idx = allocator.next(&move_wins, &move_games);
+            let result =
+                simulate_game(board, stone, valid_moves[idx], &*self.policy, &mut self.rng);
+            countdown.playout_done();
+
+            move_games[idx] += 1;
+            if result > 0.5 {
+                move_wins[idx] += 1;
+            }
+
+            if let (Some(due), Some((interval, report))) = (next_report, report.as_mut()) {
+                if Instant::now() >= due {
+                    report(&analysis(
+                        stone,
+                        started,
+                        allocation,
+                        &valid_moves,
+                        &move_wins,
+                        &move_games,
+                    ));
+                    next_report = due.checked_add(*interval);
                 }
+            }
 
-                // Run one simulation for this move
-                let result = self.simulate_game(board, stone, (x, y));
+            simulations += 1;
+            if simulations % valid_moves.len() == 0 {
+                countdown.iteration_done();
+            }
+        }
+
+        analysis(
+            stone,
+            started,
+            allocation,
+            &valid_moves,
+            &move_wins,
+            &move_games,
+        )
+    }
+}
+
+// Picks the move each simulation goes to
+struct Allocator {
+    allocation: MoveAllocation,
+    moves: usize,
+    simulations: usize,
+    // Successive halving: the moves still in, the simulations each gets
+    // in the first round of the cycle and this round, and those handed
+    // out so far this round
+    remaining: Vec<usize>,
+    cycle_size: usize,
+    round_size: usize,
+    round_done: usize,
+}
 
-                move_games[idx] += 1;
-                if result > 0.5 {
-                    move_wins[idx] += 1;
+impl Allocator {
+    fn new(allocation: MoveAllocation, moves: usize) -> Self {
+        Allocator {
+            allocation,
+            moves,
+            simulations: 0,
+            remaining: (0..moves).collect(),
+            cycle_size: 1,
+            round_size: 1,
+            round_done: 0,
+        }
+    }
+
+    fn next(&mut self, wins: &[u32], games: &[u32]) -> usize {
+        let simulations = self.simulations;
+        self.simulations += 1;
+        match self.allocation {
+            MoveAllocation::RoundRobin => simulations % self.moves,
+            // Every move is tried once before the bounds mean anything
+            MoveAllocation::Ucb1(_) if simulations < self.moves => simulations,
+            MoveAllocation::Ucb1(exploration) => {
+                let log_total = (simulations as f64).ln();
+                let bound = |idx: usize| {
+                    let games = games[idx] as f64;
+                    wins[idx] as f64 / games + exploration * (log_total / games).sqrt()
+                };
+                (0..self.moves)
+                    .max_by(|&a, &b| bound(a).total_cmp(&bound(b)))
+                    .unwrap()
+            }
+            MoveAllocation::SuccessiveHalving => {
+                if self.round_done == self.remaining.len() * self.round_size {
+                    self.next_round(wins, games);
                 }
-                _total_simulations += 1;
+                // Interleaved, so a round cut short by the budget is still even
+                let idx = self.remaining[self.round_done % self.remaining.len()];
+                self.round_done += 1;
+                idx
             }
         }
+    }
+
+    // Drops the worse half of the remaining moves, or brings all of them
+    // back once a single one is left
+    fn next_round(&mut self, wins: &[u32], games: &[u32]) {
+        if self.remaining.len() == 1 {
+            self.remaining = (0..self.moves).collect();
+            self.cycle_size *= 2;
+            self.round_size = self.cycle_size;
+        } else {
+            let win_rate = |idx: usize| wins[idx] as f64 / games[idx] as f64;
+            self.remaining
+                .sort_by(|&a, &b| win_rate(b).total_cmp(&win_rate(a)));
+            self.remaining.truncate(self.remaining.len().div_ceil(2));
+            self.round_size *= 2;
+        }
+        self.round_done = 0;
+    }
+}
+
+// Sums up the simulations so far, moves not tried yet left out unless none
+// was, when the first move stands in so running out of time straight away
+// does not end in a pass. With round robin the move with the best win rate
+// comes first, the earliest one on a tie; otherwise the move given the most
+// simulations, as the allocation already steered them to the moves that
+// looked best.
+fn analysis(
+    stone: Stone,
+    started: Instant,
+    allocation: MoveAllocation,
+    valid_moves: &[(usize, usize)],
+    move_wins: &[u32],
+    move_games: &[u32],
+) -> Analysis {
+    let prior = 1.0 / valid_moves.len() as f64;
+    let mut candidates: Vec<CandidateInfo> = valid_moves
+        .iter()
+        .zip(move_wins.iter().zip(move_games))
+        .filter(|(_, (_, &games))| games > 0)
+        .map(|(&(x, y), (&wins, &games))| CandidateInfo {
+            mv: Move::Place(x, y),
+            visits: games,
+            win_rate: wins as f64 / games as f64,
+            prior,
+            pv: vec![Move::Place(x, y)],
+        })
+        .collect();
+    if candidates.is_empty() {
+        if let Some(&(x, y)) = valid_moves.first() {
+            candidates.push(CandidateInfo {
+                mv: Move::Place(x, y),
+                visits: 0,
+                win_rate: 0.0,
+                prior,
+                pv: vec![Move::Place(x, y)],
+            });
+        }
+    }
+    match allocation {
+        MoveAllocation::RoundRobin => candidates.sort_by(|a, b| b.win_rate.total_cmp(&a.win_rate)),
+        _ => candidates.sort_by(|a, b| {
+            b.visits
+                .cmp(&a.visits)
+                .then(b.win_rate.total_cmp(&a.win_rate))
+        }),
+    }
+
+    Analysis {
+        to_move: stone,
+        visits: move_games.iter().sum(),
+        elapsed: started.elapsed(),
+        candidates,
+    }
+}
+
+fn simulate_game(
+    board: &Board,
+    stone: Stone,
+    first_move: (usize, usize),
+    policy: &dyn PlayoutPolicy,
+    rng: &mut impl Rng,
+) -> f64 {
+    // Create a new board with the same state including captured stones
+    let mut sim_board = board.clone_position();
+
+    // Apply the first move
+    if sim_board
+        .place_stone(first_move.0, first_move.1, stone)
+        .is_err()
+    {
+        // Invalid move, return loss
+        return 0.0;
+    }
+
+    let mut current_turn = stone.opposite();
+    let mut consecutive_passes = 0;
+    let mut last_move = Some(first_move);
 
-        // Select move with best win rate
-        let mut best_idx = 0;
-        let mut best_win_rate = 0.0;
+    // Play out the game with a maximum number of moves to prevent long games
+    let mut moves = 0;
+    let max_moves = board.size() * board.size() * 2;
 
-        for idx in 0..valid_moves.len() {
-            if move_games[idx] > 0 {
-                let win_rate = move_wins[idx] as f64 / move_games[idx] as f64;
-                if win_rate > best_win_rate {
-                    best_win_rate = win_rate;
-                    best_idx = idx;
+    loop {
+        match policy.pick_move(&sim_board, current_turn, last_move, rng) {
+            Some((x, y)) => {
+                // In simulation, we don't track Ko rule for performance
+                if sim_board.place_stone(x, y, current_turn).is_ok() {
+                    consecutive_passes = 0;
+                    last_move = Some((x, y));
+                }
+            }
+            None => {
+                consecutive_passes += 1;
+                last_move = None;
+                if consecutive_passes >= 2 {
+                    break;
                 }
             }
         }
 
-        // Debug output (commented out for performance)
-        // println!(
-        //     "Monte Carlo: {} simulations, best move win rate: {:.1}% ({}/{})",
-        //     total_simulations,
-        //     best_win_rate * 100.0,
-        //     move_wins[best_idx],
-        //     move_games[best_idx]
-        // );
+        current_turn = current_turn.opposite();
+
+        moves += 1;
+        if moves >= max_moves {
+            break;
+        }
+    }
+
+    // Evaluate final position
+    let (black_stones, white_stones) = sim_board.count_stones();
+    let (black_captured, white_captured) = sim_board.get_captured();
+
+    let black_score = (black_stones + black_captured) as i32;
+    let white_score = (white_stones + white_captured) as i32;
+
+    // Return win (1.0) or loss (0.0) from perspective of the original stone
+    match stone {
+        Stone::Black => {
+            if black_score > white_score {
+                1.0
+            } else {
+                0.0
+            }
+        }
+        Stone::White => {
+            if white_score > black_score {
+                1.0
+            } else {
+                0.0
+            }
+        }
+    }
+}
+
+impl Player for MonteCarloAI {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_move(&mut self, view: &GameView) -> Option<(usize, usize)> {
+        self.search(view, None).best_move().to_option()
+    }
 
-        Some(valid_moves[best_idx])
+    fn analyze(
+        &mut self,
+        view: &GameView,
+        interval: Duration,
+        report: &mut dyn FnMut(&Analysis),
+    ) -> Option<Analysis> {
+        Some(self.search(view, Some((interval, report))))
     }
 }
@@ -1,162 +1,871 @@
+use crate::ai::budget::{Countdown, SearchBudget};
+use crate::ai::playout::{get_valid_moves, LightPolicy, PlayoutPolicy};
+use crate::analysis::{Analysis, CandidateInfo, Reporter};
 use crate::board::{Board, Stone};
+use crate::game::Move;
 use crate::player::Player;
-use std::cell::RefCell;
-use std::rc::Rc;
+use crate::result::GameResult;
+use crate::rules::{KoRule, Rules};
+use crate::view::GameView;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
 use std::time::{Duration, Instant};
 
+// Longest principal variation an analysis lists
+const MAX_PV_LENGTH: usize = 20;
+// How often a search being analysed checks whether a report is due
+const REPORT_POLL: Duration = Duration::from_millis(10);
+
+// Positions leading to the one being searched, so the tree never
+// contains moves the ko rule forbids
 #[derive(Clone)]
+struct KoHistory {
+    rule: KoRule,
+    positions: Vec<u64>,
+    situations: Vec<u64>,
+}
+
+impl KoHistory {
+    fn new(view: &GameView) -> Self {
+        let mut history = KoHistory {
+            rule: view.rules.ko,
+            positions: view.board_history.to_vec(),
+            situations: view.situation_history.to_vec(),
+        };
+        // A bare position still lets the simple ko rule work below the root
+        if history.positions.is_empty() {
+            history.push(view.board, view.to_move);
+        }
+        history
+    }
+
+    // Whether `board`, just reached by a move of `stone`, repeats an
+    // earlier position
+    fn forbids(&self, board: &Board, stone: Stone) -> bool {
+        self.rule.is_repetition(
+            &self.positions,
+            &self.situations,
+            board.get_hash(),
+            board.get_situation_hash(stone.opposite()),
+        )
+    }
+
+    fn push(&mut self, board: &Board, to_move: Stone) {
+        self.positions.push(board.get_hash());
+        self.situations.push(board.get_situation_hash(to_move));
+    }
+
+    // Like Game, a pass only adds to the situations
+    fn push_pass(&mut self, board: &Board, to_move: Stone) {
+        self.situations.push(board.get_situation_hash(to_move));
+    }
+
+    fn len(&self) -> (usize, usize) {
+        (self.positions.len(), self.situations.len())
+    }
+
+    fn truncate(&mut self, (positions, situations): (usize, usize)) {
+        self.positions.truncate(positions);
+        self.situations.truncate(situations);
+    }
+}
+
+// Nodes are shared between search threads: statistics are atomics and
+// the parts that change on expansion sit behind a lock
 struct MctsNode {
-    visits: u32,
-    black_wins: f64, // Number of times Black won in simulations from this node
-    move_pos: Option<(usize, usize)>, // The move that led to this position (None for root)
+    visits: AtomicU32,
+    black_wins: AtomicU32, // Number of times Black won in simulations from this node
+    virtual_loss: AtomicU32, // Searches currently passing through this node
     player_to_move: Stone, // Whose turn it is to play FROM this position
-    children: Vec<Rc<RefCell<MctsNode>>>,
-    untried_moves: Vec<(usize, usize)>,
+    key: NodeKey,
+    expansion: Mutex<Expansion>,
+}
+
+// The position a node stands for. Scoring counts captures, so the same
+// stones reached with different captures are different positions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct NodeKey {
+    situation: u64, // Hash of the position with the side to move
+    passes: usize,  // Consecutive passes leading here, 2 ends the game
+    captures: i64,  // Stones Black captured minus those White captured
+}
+
+impl NodeKey {
+    fn new(board: &Board, to_move: Stone, passes: usize) -> Self {
+        let (black, white) = board.get_captured();
+        NodeKey {
+            situation: board.get_situation_hash(to_move),
+            passes,
+            captures: black as i64 - white as i64,
+        }
+    }
+}
+
+struct Expansion {
+    children: Vec<Edge>,
+    untried_moves: Vec<Move>,
+}
+
+// A move and the position it leads to. The move and its AMAF statistics
+// are kept here rather than in the node because transposed nodes are
+// reached by different moves.
+#[derive(Clone)]
+struct Edge {
+    mv: Move,
+    node: Arc<MctsNode>,
+    amaf: Arc<AmafStats>,
+}
+
+impl Edge {
+    fn new(mv: Move, node: Arc<MctsNode>) -> Self {
+        Edge {
+            mv,
+            node,
+            amaf: Arc::new(AmafStats::default()),
+        }
+    }
+}
+
+#[derive(Default)]
+struct AmafStats {
+    visits: AtomicU32, // Simulations in which the parent's player played this move later
+    black_wins: AtomicU32,
 }
 
 impl MctsNode {
-    fn new(
-        player_to_move: Stone,
-        move_pos: Option<(usize, usize)>,
-        available_moves: Vec<(usize, usize)>,
-    ) -> Self {
+    fn new(player_to_move: Stone, available_moves: Vec<Move>, key: NodeKey) -> Self {
         MctsNode {
-            visits: 0,
-            black_wins: 0.0,
-            move_pos,
+            visits: AtomicU32::new(0),
+            black_wins: AtomicU32::new(0),
+            virtual_loss: AtomicU32::new(0),
             player_to_move,
-            children: Vec::new(),
-            untried_moves: available_moves,
+            key,
+            expansion: Mutex::new(Expansion {
+                children: Vec::new(),
+                untried_moves: available_moves,
+            }),
         }
     }
 
-    fn uct_value(&self, parent_visits: u32, exploration: f64, parent_player: Stone) -> f64 {
-        if self.visits == 0 {
+    fn visits(&self) -> u32 {
+        self.visits.load(Ordering::Relaxed)
+    }
+
+    fn is_terminal(&self) -> bool {
+        self.key.passes >= 2
+    }
+
+    // How promising this node looks to the parent choosing it, with `amaf`
+    // the statistics of the edge leading here
+    fn uct_value(
+        &self,
+        amaf: &AmafStats,
+        parent_visits: u32,
+        exploration: f64,
+        rave: RaveSchedule,
+        parent_player: Stone,
+    ) -> f64 {
+        let visits = self.visits();
+        // Searches still running below this node count as lost for the
+        // side choosing it, which steers other threads elsewhere
+        let pending = visits + self.virtual_loss.load(Ordering::Relaxed);
+        if pending == 0 {
             f64::INFINITY
         } else {
             // This node contains statistics collected from the position
             // AFTER parent_player has made a move. The statistics show
             // how often Black wins from this position.
-            let win_rate = match parent_player {
-                Stone::Black => self.black_wins / self.visits as f64,
-                Stone::White => 1.0 - (self.black_wins / self.visits as f64),
+            let black_wins = self.black_wins.load(Ordering::Relaxed) as f64;
+            let wins = match parent_player {
+                Stone::Black => black_wins,
+                Stone::White => (visits as f64 - black_wins).max(0.0),
             };
+            let mut win_rate = wins / pending as f64;
+
+            // Blend in the all-moves-as-first estimate while real visits are few
+            let amaf_visits = amaf.visits.load(Ordering::Relaxed);
+            let beta = rave.beta(pending, amaf_visits);
+            if beta > 0.0 {
+                let amaf_black_wins = amaf.black_wins.load(Ordering::Relaxed) as f64;
+                let amaf_wins = match parent_player {
+                    Stone::Black => amaf_black_wins,
+                    Stone::White => (amaf_visits as f64 - amaf_black_wins).max(0.0),
+                };
+                let amaf_rate = amaf_wins / amaf_visits as f64;
+                win_rate = (1.0 - beta) * win_rate + beta * amaf_rate;
+            }
 
             let exploration_term =
-                exploration * ((parent_visits as f64).ln() / self.visits as f64).sqrt();
+                exploration * ((parent_visits as f64).ln() / pending as f64).sqrt();
             win_rate + exploration_term
         }
     }
 
-    fn select_child(&self, exploration: f64) -> Option<Rc<RefCell<MctsNode>>> {
-        if self.children.is_empty() {
+    // Share of the simulations through this node won by `player`
+    fn win_rate(&self, player: Stone) -> f64 {
+        let visits = self.visits() as f64;
+        let black_wins = self.black_wins.load(Ordering::Relaxed) as f64;
+        match player {
+            Stone::Black => black_wins / visits,
+            Stone::White => (visits - black_wins) / visits,
+        }
+    }
+
+    // The child to descend into, or None while this node still has
+    // untried moves (or no moves at all)
+    fn select_child(&self, exploration: f64, rave: RaveSchedule) -> Option<Edge> {
+        let expansion = self.expansion.lock().unwrap();
+        if !expansion.untried_moves.is_empty() || expansion.children.is_empty() {
             return None;
         }
 
+        // Other threads may have expanded every child before a single
+        // playout came back up here, so searches still under way count too
+        let parent_visits = (self.visits() + self.virtual_loss.load(Ordering::Relaxed)).max(1);
         let parent_player = self.player_to_move;
-        self.children
+        expansion
+            .children
             .iter()
             .max_by(|a, b| {
-                let a_val = a
-                    .borrow()
-                    .uct_value(self.visits, exploration, parent_player);
-                let b_val = b
-                    .borrow()
-                    .uct_value(self.visits, exploration, parent_player);
-                a_val.partial_cmp(&b_val).unwrap()
+                let a_val =
+                    a.node
+                        .uct_value(&a.amaf, parent_visits, exploration, rave, parent_player);
+                let b_val =
+                    b.node
+                        .uct_value(&b.amaf, parent_visits, exploration, rave, parent_player);
+                a_val.total_cmp(&b_val)
             })
             .cloned()
     }
 
-    fn expand(&mut self, board: &Board, current_player: Stone) -> Option<Rc<RefCell<MctsNode>>> {
-        if self.untried_moves.is_empty() {
-            return None;
-        }
-
-        // Pick a random untried move
-        let idx = rand::random::<usize>() % self.untried_moves.len();
-        let chosen_move = self.untried_moves.remove(idx);
-
-        // Get valid moves for the child node
-        let mut child_board = board.clone();
-        // Place stone for the current player (whose turn it is from this node)
-        if child_board
-            .place_stone(chosen_move.0, chosen_move.1, current_player)
-            .is_ok()
-        {
+    // Adds a child for one of the untried moves. Once the table is full only
+    // the root still grows, so there is always a move to pick.
+    fn expand(
+        &self,
+        board: &mut Board,
+        current_player: Stone,
+        history: &KoHistory,
+        table: Option<&TranspositionTable>,
+        is_root: bool,
+        rng: &mut impl Rng,
+    ) -> Option<Edge> {
+        let mut expansion = self.expansion.lock().unwrap();
+        while !expansion.untried_moves.is_empty() {
+            // Pick a random untried move. Pass is kept last and only tried
+            // once every placement has been, so thin statistics early in a
+            // search cannot make it look best.
+            let untried = expansion.untried_moves.len();
+            let placements = match expansion.untried_moves.last() {
+                Some(Move::Pass) if untried > 1 => untried - 1,
+                _ => untried,
+            };
+            let idx = rng.gen_range(0..placements);
+            let chosen_move = expansion.untried_moves.remove(idx);
             // Child will be opponent's turn
             let child_stone = current_player.opposite();
-            // Ko rule is handled at the Game level, not in MCTS
-            let child_moves = get_valid_moves(&child_board, child_stone);
 
-            let child_node = Rc::new(RefCell::new(MctsNode::new(
-                child_stone,
-                Some(chosen_move),
-                child_moves,
-            )));
+            let child_node = match chosen_move {
+                Move::Place(x, y) => {
+                    // Place stone for the current player (whose turn it is from this node)
+                    if board.make_move(x, y, current_player).is_err() {
+                        return None;
+                    }
+                    // Moves repeating an earlier position are dropped for good
+                    if history.forbids(board, current_player) {
+                        board.unmake_move();
+                        continue;
+                    }
 
-            self.children.push(child_node.clone());
-            Some(child_node)
-        } else {
-            None
+                    let child_key = NodeKey::new(board, child_stone, 0);
+                    let child_node = find_or_create(table, child_key, is_root, || {
+                        let child_moves = candidate_moves(board, child_stone);
+                        MctsNode::new(child_stone, child_moves, child_key)
+                    });
+                    board.unmake_move();
+                    child_node
+                }
+                Move::Pass => {
+                    let child_key = NodeKey::new(board, child_stone, self.key.passes + 1);
+                    find_or_create(table, child_key, is_root, || {
+                        // Nothing can be played after the game has ended
+                        let child_moves = if child_key.passes >= 2 {
+                            Vec::new()
+                        } else {
+                            candidate_moves(board, child_stone)
+                        };
+                        MctsNode::new(child_stone, child_moves, child_key)
+                    })
+                }
+            };
+            // The table is full: the move stays untried and the search
+            // goes on with playouts from here
+            let Some(child_node) = child_node else {
+                expansion.untried_moves.insert(idx, chosen_move);
+                return None;
+            };
+            let edge = Edge::new(chosen_move, child_node);
+
+            expansion.children.push(edge.clone());
+            return Some(edge);
         }
+        None
     }
 
-    fn update(&mut self, black_won: bool) {
-        self.visits += 1;
+    fn update(&self, black_won: bool) {
+        self.visits.fetch_add(1, Ordering::Relaxed);
         if black_won {
-            self.black_wins += 1.0;
+            self.black_wins.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    // Credits every child whose move this node's player went on to play
+    // somewhere later in the simulation. `first_player` holds, per point,
+    // the color that played there first after this node.
+    fn update_amaf(&self, first_player: &[Option<Stone>], board_size: usize, black_won: bool) {
+        let expansion = self.expansion.lock().unwrap();
+        for edge in &expansion.children {
+            let Move::Place(x, y) = edge.mv else {
+                continue;
+            };
+            if first_player[y * board_size + x] == Some(self.player_to_move) {
+                edge.amaf.visits.fetch_add(1, Ordering::Relaxed);
+                if black_won {
+                    edge.amaf.black_wins.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+    }
+
+    // Adds the root statistics of independently searched trees into this
+    // (fresh) root, merging children that play the same move. Moves none of
+    // the trees got to stay untried.
+    fn merge_roots(&self, trees: &[SearchTree]) {
+        let mut expansion = self.expansion.lock().unwrap();
+        for SearchTree { root: tree, .. } in trees {
+            self.visits.fetch_add(tree.visits(), Ordering::Relaxed);
+            self.black_wins
+                .fetch_add(tree.black_wins.load(Ordering::Relaxed), Ordering::Relaxed);
+
+            for edge in &tree.expansion.lock().unwrap().children {
+                let child = &edge.node;
+                let merged = match expansion
+                    .children
+                    .iter()
+                    .find(|merged| merged.mv == edge.mv)
+                {
+                    Some(merged) => merged.node.clone(),
+                    None => {
+                        let merged =
+                            Arc::new(MctsNode::new(child.player_to_move, Vec::new(), child.key));
+                        expansion.children.push(Edge::new(edge.mv, merged.clone()));
+                        merged
+                    }
+                };
+                merged.visits.fetch_add(child.visits(), Ordering::Relaxed);
+                merged
+                    .black_wins
+                    .fetch_add(child.black_wins.load(Ordering::Relaxed), Ordering::Relaxed);
+            }
+        }
+        let Expansion {
+            children,
+            untried_moves,
+        } = &mut *expansion;
+        untried_moves.retain(|mv| children.iter().all(|edge| edge.mv != *mv));
+    }
+}
+
+// Nodes of one search by position, so that move orders reaching the same
+// position share a node and its statistics, turning the tree into a DAG.
+// Every node of the DAG is in the table, so its size bounds the memory
+// the search takes. Entries are weak: nodes dropped with the rest of an
+// old tree free their slot. A ko cycle can lead a node back to one above
+// it, and such cycles are broken up here once they are out of the tree.
+struct TranspositionTable {
+    nodes: Mutex<HashMap<NodeKey, Weak<MctsNode>>>,
+    capacity: usize,
+    hits: AtomicU64, // Positions found in the table during the current search
+}
+
+impl TranspositionTable {
+    fn new(capacity: usize) -> Self {
+        TranspositionTable {
+            nodes: Mutex::new(HashMap::new()),
+            capacity,
+            hits: AtomicU64::new(0),
+        }
+    }
+
+    // The node of `key`, created if there is room for it or `force` is set,
+    // None otherwise
+    fn find_or_create(
+        &self,
+        key: NodeKey,
+        force: bool,
+        create: impl FnOnce() -> MctsNode,
+    ) -> Option<Arc<MctsNode>> {
+        let mut nodes = self.nodes.lock().unwrap();
+        if let Some(node) = nodes.get(&key).and_then(Weak::upgrade) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Some(node);
+        }
+        // A dead entry for the key is replaced without growing the table
+        if !force && nodes.len() >= self.capacity && !nodes.contains_key(&key) {
+            return None;
+        }
+
+        let node = Arc::new(create());
+        nodes.insert(key, Arc::downgrade(&node));
+        Some(node)
+    }
+
+    // Forgets the nodes that cannot be reached from `root` any more and
+    // resets the hit count, before a new search. Their children are let go
+    // of, as a cycle would otherwise keep them alive for good.
+    fn prepare(&self, root: &Arc<MctsNode>) {
+        let reachable = reachable_nodes(root);
+        self.nodes
+            .lock()
+            .unwrap()
+            .retain(|_, node| match node.upgrade() {
+                Some(node) if reachable.contains(&Arc::as_ptr(&node)) => true,
+                Some(node) => {
+                    node.expansion.lock().unwrap().children.clear();
+                    false
+                }
+                None => false,
+            });
+        self.hits.store(0, Ordering::Relaxed);
+    }
+
+    fn len(&self) -> usize {
+        self.nodes.lock().unwrap().len()
+    }
+}
+
+// A tree thrown away with its table may still hold cycles
+impl Drop for TranspositionTable {
+    fn drop(&mut self) {
+        let nodes = self.nodes.get_mut().unwrap();
+        for node in nodes.values().filter_map(Weak::upgrade) {
+            node.expansion.lock().unwrap().children.clear();
+        }
+    }
+}
+
+// Every node below `root`, by address
+fn reachable_nodes(root: &Arc<MctsNode>) -> HashSet<*const MctsNode> {
+    let mut reachable = HashSet::from([Arc::as_ptr(root)]);
+    let mut pending = vec![root.clone()];
+    while let Some(node) = pending.pop() {
+        for edge in &node.expansion.lock().unwrap().children {
+            if reachable.insert(Arc::as_ptr(&edge.node)) {
+                pending.push(edge.node.clone());
+            }
+        }
+    }
+    reachable
+}
+
+// Without a table every position gets a node of its own
+fn find_or_create(
+    table: Option<&TranspositionTable>,
+    key: NodeKey,
+    force: bool,
+    create: impl FnOnce() -> MctsNode,
+) -> Option<Arc<MctsNode>> {
+    match table {
+        Some(table) => table.find_or_create(key, force, create),
+        None => Some(Arc::new(create())),
+    }
+}
+
+// A search tree, with the transposition table of its nodes when
+// transpositions are on
+struct SearchTree {
+    root: Arc<MctsNode>,
+    table: Option<TranspositionTable>,
+}
+
+impl SearchTree {
+    fn hits(&self) -> u64 {
+        self.table
+            .as_ref()
+            .map_or(0, |table| table.hits.load(Ordering::Relaxed))
+    }
+
+    fn table_nodes(&self) -> usize {
+        self.table.as_ref().map_or(0, TranspositionTable::len)
+    }
+}
+
+// How much weight the AMAF (all moves as first) statistics of a child get
+// next to its real visits when RAVE is on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RaveSchedule {
+    Off,
+    // beta = sqrt(k / (3n + k)): the AMAF value is worth about k real visits
+    Equivalence(f64),
+    // Silver's minimum-MSE schedule with the given AMAF bias b:
+    // beta = m / (n + m + 4 b^2 n m) for n real and m AMAF visits
+    MinimumMse(f64),
+}
+
+impl Default for RaveSchedule {
+    fn default() -> Self {
+        RaveSchedule::Equivalence(1000.0)
+    }
+}
+
+impl RaveSchedule {
+    // Weight of the AMAF value for a child with `visits` real and
+    // `amaf_visits` AMAF visits, between 0 and 1
+    pub fn beta(&self, visits: u32, amaf_visits: u32) -> f64 {
+        if amaf_visits == 0 {
+            return 0.0;
+        }
+        let n = visits as f64;
+        match *self {
+            RaveSchedule::Off => 0.0,
+            RaveSchedule::Equivalence(k) => (k / (3.0 * n + k)).sqrt(),
+            RaveSchedule::MinimumMse(bias) => {
+                let m = amaf_visits as f64;
+                m / (n + m + 4.0 * bias * bias * n * m)
+            }
+        }
+    }
+}
+
+// How the move is picked among the root children once the search is over
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum MoveSelection {
+    #[default]
+    MostVisits,
+    HighestWinRate,
+    // Highest lower bound of the win rate, z standard errors below it,
+    // which keeps rarely visited moves with lucky results from winning
+    LowerConfidenceBound(f64),
+}
+
+impl MoveSelection {
+    // How good a root child looks to `player`, who chooses among them
+    fn score(&self, child: &MctsNode, player: Stone) -> f64 {
+        let visits = child.visits();
+        if visits == 0 {
+            return f64::NEG_INFINITY;
+        }
+        match *self {
+            MoveSelection::MostVisits => visits as f64,
+            MoveSelection::HighestWinRate => child.win_rate(player),
+            MoveSelection::LowerConfidenceBound(z) => {
+                let win_rate = child.win_rate(player);
+                win_rate - z * (win_rate * (1.0 - win_rate) / visits as f64).sqrt()
+            }
+        }
+    }
+}
+
+// Search and evaluation parameters of Mcts, for tuning
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MctsConfig {
+    exploration: f64,
+    rave: RaveSchedule,
+    max_playout_moves: Option<usize>,
+    eye_bonus: i32,
+    draw_margin: i32,
+    move_selection: MoveSelection,
+    transpositions: Option<usize>,
+}
+
+impl Default for MctsConfig {
+    fn default() -> Self {
+        MctsConfig {
+            exploration: 1.4, // Standard UCT constant
+            rave: RaveSchedule::default(),
+            max_playout_moves: None,
+            eye_bonus: 5,
+            draw_margin: 2,
+            move_selection: MoveSelection::default(),
+            transpositions: None,
         }
     }
 }
 
+impl MctsConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // The UCT exploration constant
+    pub fn with_exploration(mut self, exploration: f64) -> Self {
+        self.exploration = exploration;
+        self
+    }
+
+    pub fn with_rave(mut self, rave: RaveSchedule) -> Self {
+        self.rave = rave;
+        self
+    }
+
+    // Moves after which a playout is scored as it stands, one move per
+    // point of the board by default
+    pub fn with_max_playout_moves(mut self, moves: usize) -> Self {
+        self.max_playout_moves = Some(moves);
+        self
+    }
+
+    // Points a side gets at the end of a playout for having two eyes
+    pub fn with_eye_bonus(mut self, bonus: i32) -> Self {
+        self.eye_bonus = bonus;
+        self
+    }
+
+    // Playouts ending with scores at most this far apart count as half a win
+    pub fn with_draw_margin(mut self, margin: i32) -> Self {
+        self.draw_margin = margin;
+        self
+    }
+
+    pub fn with_move_selection(mut self, selection: MoveSelection) -> Self {
+        self.move_selection = selection;
+        self
+    }
+
+    // Shares one node between all move orders reaching a position. The DAG
+    // grows to at most `max_nodes` nodes per tree (beyond the root's
+    // children, which are always added), after which the search only runs
+    // playouts from its leaves.
+    pub fn with_transpositions(mut self, max_nodes: usize) -> Self {
+        self.transpositions = Some(max_nodes);
+        self
+    }
+
+    pub fn exploration(&self) -> f64 {
+        self.exploration
+    }
+
+    pub fn rave(&self) -> RaveSchedule {
+        self.rave
+    }
+
+    pub fn max_playout_moves(&self, board_size: usize) -> usize {
+        self.max_playout_moves.unwrap_or(board_size * board_size)
+    }
+
+    pub fn eye_bonus(&self) -> i32 {
+        self.eye_bonus
+    }
+
+    pub fn draw_margin(&self) -> i32 {
+        self.draw_margin
+    }
+
+    pub fn move_selection(&self) -> MoveSelection {
+        self.move_selection
+    }
+
+    // Node limit of the transposition DAG, None for a plain tree
+    pub fn transpositions(&self) -> Option<usize> {
+        self.transpositions
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Parallelism {
+    // All threads search one shared tree, spread out by virtual loss
+    #[default]
+    Tree,
+    // Every thread searches a tree of its own and the root children
+    // are merged at the end
+    Root,
+}
+
 pub struct Mcts {
     name: String,
-    time_limit: Duration,
-    exploration: f64,
+    budget: SearchBudget,
+    config: MctsConfig,
+    threads: usize,
+    parallelism: Parallelism,
+    // The last search, kept so the next search can continue from the
+    // subtree of the position that was actually reached
+    tree: Option<SearchTree>,
+    reused_visits: u32,
+    last_playouts: u64,
+    transposition_hits: u64,
+    table_nodes: usize,
+    // Seeds the per-thread generators of every search
+    rng: StdRng,
+    policy: Arc<dyn PlayoutPolicy>,
+    rules: Rules, // Scores games that end by two passes inside the tree
 }
 
 impl Mcts {
     pub fn new(time_seconds: u64) -> Self {
-        Mcts {
-            name: format!("MCTS AI ({}s)", time_seconds),
-            time_limit: Duration::from_secs(time_seconds),
-            exploration: 1.4, // Standard UCT constant
-        }
+        Self::new_with_budget(SearchBudget::from_secs(time_seconds))
     }
 
     pub fn new_with_millis(time_millis: u64) -> Self {
-        Mcts {
-            name: format!("MCTS AI ({:.1}s)", time_millis as f64 / 1000.0),
-            time_limit: Duration::from_millis(time_millis),
-            exploration: 1.4, // Standard UCT constant
+        Self::new_with_budget(SearchBudget::from_millis(time_millis))
+    }
+
+    pub fn new_with_budget(budget: SearchBudget) -> Self {
+        let mut mcts = Mcts {
+            name: String::new(),
+            budget,
+            config: MctsConfig::default(),
+            threads: 1,
+            parallelism: Parallelism::default(),
+            tree: None,
+            reused_visits: 0,
+            last_playouts: 0,
+            transposition_hits: 0,
+            table_nodes: 0,
+            rng: StdRng::from_entropy(),
+            policy: Arc::new(LightPolicy),
+            rules: Rules::default(),
+        };
+        mcts.update_name();
+        mcts
+    }
+
+    // Searches with `threads` threads (1 searches on the calling thread)
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self.update_name();
+        self
+    }
+
+    pub fn with_parallelism(mut self, parallelism: Parallelism) -> Self {
+        self.parallelism = parallelism;
+        self.update_name();
+        self
+    }
+
+    pub fn with_config(mut self, config: MctsConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn with_rave(mut self, rave: RaveSchedule) -> Self {
+        self.config.rave = rave;
+        self
+    }
+
+    // Makes searches reproducible under an iteration or playout budget.
+    // Tree parallelism with several threads still depends on scheduling.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+
+    // Plays the simulations with `policy` instead of light playouts
+    pub fn with_playout_policy(mut self, policy: impl PlayoutPolicy + 'static) -> Self {
+        self.policy = Arc::new(policy);
+        self.update_name();
+        self
+    }
+
+    fn update_name(&mut self) {
+        let mut details = match (self.threads, self.parallelism) {
+            (1, _) => self.budget.to_string(),
+            (threads, Parallelism::Tree) => format!("{}, {} threads", self.budget, threads),
+            (threads, Parallelism::Root) => format!("{}, {} root threads", self.budget, threads),
+        };
+        if self.policy.name() != LightPolicy.name() {
+            details += &format!(", {} playouts", self.policy.name());
+        }
+        self.name = format!("MCTS AI ({})", details);
+    }
+
+    pub fn config(&self) -> &MctsConfig {
+        &self.config
+    }
+
+    // Visits the root of the last search already had from earlier moves
+    pub fn reused_visits(&self) -> u32 {
+        self.reused_visits
+    }
+
+    // Playouts run by the last search, over all threads
+    pub fn last_playouts(&self) -> u64 {
+        self.last_playouts
+    }
+
+    // Times the last search reached a position already in the tree by
+    // another move order (0 unless transpositions are on)
+    pub fn transposition_hits(&self) -> u64 {
+        self.transposition_hits
+    }
+
+    // Nodes the transposition tables held after the last search, over all
+    // trees (0 unless transpositions are on)
+    pub fn table_nodes(&self) -> usize {
+        self.table_nodes
+    }
+
+    // Finds the position `key` among the last search tree and the two plies
+    // below it (our move and the opponent's reply)
+    fn take_subtree(&mut self, key: NodeKey) -> Option<SearchTree> {
+        let SearchTree { root, table } = self.tree.take()?;
+        if root.key == key {
+            return Some(SearchTree { root, table });
         }
+
+        let expansion = root.expansion.lock().unwrap();
+        let root = expansion
+            .children
+            .iter()
+            .flat_map(|edge| {
+                let child = &edge.node;
+                let mut nodes = vec![child.clone()];
+                let expansion = child.expansion.lock().unwrap();
+                nodes.extend(expansion.children.iter().map(|edge| edge.node.clone()));
+                nodes
+            })
+            .find(|node| node.key == key)?;
+        Some(SearchTree { root, table })
     }
 
-    fn simulate(&self, board: &Board, stone: Stone) -> f64 {
-        let mut sim_board = board.clone();
+    // Plays a game from `board`, reached after `passes` consecutive passes
+    // with `last_move` the placement before, appending the moves to `played`
+    fn simulate(
+        &self,
+        board: &Board,
+        stone: Stone,
+        passes: usize,
+        mut last_move: Option<(usize, usize)>,
+        played: &mut Vec<((usize, usize), Stone)>,
+        rng: &mut impl Rng,
+    ) -> f64 {
+        let mut sim_board = board.clone_position();
         let mut current_turn = stone;
-        let mut consecutive_passes = 0;
+        let mut consecutive_passes = passes;
 
         let mut moves = 0;
-        let max_moves = board.size() * board.size(); // Further reduced
+        let max_moves = self.config.max_playout_moves(board.size());
 
         loop {
-            // Use get_valid_moves to respect eye rules
-            let valid_moves = get_valid_moves(&sim_board, current_turn);
-
-            if valid_moves.is_empty() {
-                consecutive_passes += 1;
-                if consecutive_passes >= 2 {
-                    break;
+            match self
+                .policy
+                .pick_move(&sim_board, current_turn, last_move, rng)
+            {
+                Some((x, y)) => {
+                    if sim_board.place_stone(x, y, current_turn).is_ok() {
+                        consecutive_passes = 0;
+                        played.push(((x, y), current_turn));
+                        last_move = Some((x, y));
+                    }
                 }
-            } else {
-                // Pick a random valid move
-                let idx = rand::random::<usize>() % valid_moves.len();
-                let (x, y) = valid_moves[idx];
-
-                if sim_board.place_stone(x, y, current_turn).is_ok() {
-                    consecutive_passes = 0;
+                None => {
+                    consecutive_passes += 1;
+                    last_move = None;
+                    if consecutive_passes >= 2 {
+                        break;
+                    }
                 }
             }
 
@@ -168,116 +877,364 @@ impl Mcts {
             }
         }
 
-        // Evaluate final position
-        let (black_stones, white_stones) = sim_board.count_stones();
-        let (black_captured, white_captured) = sim_board.get_captured();
-
-        let black_score = (black_stones + black_captured) as i32;
-        let white_score = (white_stones + white_captured) as i32;
-
-        // Also consider eye count for more stable evaluation
-        let black_eyes = sim_board.count_eyes_for_color(Stone::Black);
-        let white_eyes = sim_board.count_eyes_for_color(Stone::White);
-
-        // Bonus for having 2+ eyes (alive group)
-        let black_bonus = if black_eyes >= 2 { 5 } else { 0 };
-        let white_bonus = if white_eyes >= 2 { 5 } else { 0 };
-
-        let final_black_score = black_score + black_bonus;
-        let final_white_score = white_score + white_bonus;
-
-        // Return win probability with small margin for draws
-        match stone {
-            Stone::Black => {
-                if final_black_score > final_white_score + 2 {
-                    1.0
-                } else if final_white_score > final_black_score + 2 {
-                    0.0
-                } else {
-                    0.5 // Close game
-                }
+        self.score(&sim_board, stone)
+    }
+
+    // Scores a finished playout or a game ended by two passes, 1.0 if
+    // `stone` won, 0.0 if it lost and 0.5 if it was close. Both count under
+    // the rules being played, komi included, so passing and playing on are
+    // judged alike. A side with two eyes or more gets the eye bonus, and
+    // totals within the draw margin count as close.
+    fn score(&self, board: &Board, stone: Stone) -> f64 {
+        let (black, white) = self.rules.score(board);
+        let bonus = |stone: Stone| {
+            if board.count_eyes_for_color(stone) >= 2 {
+                self.config.eye_bonus as f64
+            } else {
+                0.0
             }
-            Stone::White => {
-                if final_white_score > final_black_score + 2 {
-                    1.0
-                } else if final_black_score > final_white_score + 2 {
-                    0.0
-                } else {
-                    0.5 // Close game
+        };
+        let black_total = black.total + bonus(Stone::Black);
+        let white_total = white.total + bonus(Stone::White);
+        let (own, other) = match stone {
+            Stone::Black => (black_total, white_total),
+            Stone::White => (white_total, black_total),
+        };
+
+        let margin = self.config.draw_margin as f64;
+        if own > other + margin {
+            1.0
+        } else if other > own + margin {
+            0.0
+        } else {
+            0.5 // Close game
+        }
+    }
+
+    fn run_mcts(&mut self, view: &GameView, mut report: Option<Reporter>) -> Analysis {
+        let (board, stone) = (view.board, view.to_move);
+        let started = Instant::now();
+        let history = KoHistory::new(view);
+        let passes = view.consecutive_passes.min(1);
+        self.rules = view.rules;
+
+        let key = NodeKey::new(board, stone, passes);
+        // Root-parallel trees are thrown away after merging, so there is
+        // nothing to reuse
+        let reused = match self.parallelism {
+            Parallelism::Tree => self.take_subtree(key),
+            Parallelism::Root => None,
+        };
+
+        let tree = match reused {
+            Some(tree) => tree,
+            None => {
+                let mut board = board.clone_position();
+                let mut moves: Vec<_> = get_valid_moves(&board, stone)
+                    .into_iter()
+                    .filter(|&(x, y)| {
+                        board.make_move(x, y, stone).unwrap();
+                        let forbidden = history.forbids(&board, stone);
+                        board.unmake_move();
+                        !forbidden
+                    })
+                    .map(|(x, y)| Move::Place(x, y))
+                    .collect();
+
+                // Passing is all that is left, no need to search
+                if moves.is_empty() {
+                    return Analysis {
+                        to_move: stone,
+                        visits: 0,
+                        elapsed: started.elapsed(),
+                        candidates: Vec::new(),
+                    };
                 }
+                moves.push(Move::Pass);
+
+                self.new_tree(MctsNode::new(stone, moves, key))
             }
+        };
+        let root = tree.root.clone();
+        self.reused_visits = root.visits();
+        if let Some(table) = &tree.table {
+            table.prepare(&root);
         }
+
+        // Drawing every thread's seed up front keeps the search independent
+        // of which thread runs first
+        let mut rngs: Vec<StdRng> = (0..self.threads)
+            .map(|_| StdRng::seed_from_u64(self.rng.gen()))
+            .collect();
+        let mut countdowns = Countdown::start(self.budget).split(self.threads);
+        let search = &*self;
+        let root_parallel = self.threads > 1 && self.parallelism == Parallelism::Root;
+
+        // Root-parallel threads each get a tree (and table) of their own
+        let separate: Vec<SearchTree> = if root_parallel {
+            (0..self.threads)
+                .map(|_| {
+                    let moves = root.expansion.lock().unwrap().untried_moves.clone();
+                    search.new_tree(MctsNode::new(stone, moves, key))
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let trees: Vec<&SearchTree> = if root_parallel {
+            separate.iter().collect()
+        } else {
+            vec![&tree; self.threads]
+        };
+        // Where the principal variations are read from
+        let lines: Vec<&MctsNode> = if root_parallel {
+            separate.iter().map(|tree| &*tree.root).collect()
+        } else {
+            vec![&*root]
+        };
+
+        let playouts = if self.threads == 1 && report.is_none() {
+            let countdown = countdowns.pop().unwrap();
+            search.search(&tree, board, stone, history, countdown, &mut rngs[0])
+        } else {
+            thread::scope(|scope| {
+                let workers: Vec<_> = trees
+                    .iter()
+                    .zip(countdowns)
+                    .zip(&mut rngs)
+                    .map(|((tree, countdown), rng)| {
+                        let history = history.clone();
+                        scope.spawn(move || {
+                            search.search(tree, board, stone, history, countdown, rng)
+                        })
+                    })
+                    .collect();
+
+                // The calling thread only watches the search, reporting on it
+                // every `interval` until all the workers are done
+                if let Some((interval, report)) = report.as_mut() {
+                    let mut next_report = started.checked_add(*interval);
+                    while !workers.iter().all(|worker| worker.is_finished()) {
+                        thread::sleep(REPORT_POLL.min(*interval));
+                        let Some(due) = next_report.filter(|&due| Instant::now() >= due) else {
+                            continue;
+                        };
+                        let analysis = if root_parallel {
+                            let merged = MctsNode::new(stone, Vec::new(), key);
+                            merged.merge_roots(&separate);
+                            search.analysis(&merged, &lines, started.elapsed())
+                        } else {
+                            search.analysis(&root, &lines, started.elapsed())
+                        };
+                        report(&analysis);
+                        next_report = due.checked_add(*interval);
+                    }
+                }
+                workers.into_iter().map(|w| w.join().unwrap()).sum()
+            })
+        };
+
+        let (hits, table_nodes) = if root_parallel {
+            root.merge_roots(&separate);
+            (
+                separate.iter().map(SearchTree::hits).sum(),
+                separate.iter().map(SearchTree::table_nodes).sum(),
+            )
+        } else {
+            (tree.hits(), tree.table_nodes())
+        };
+        self.last_playouts = playouts;
+        self.transposition_hits = hits;
+        self.table_nodes = table_nodes;
+
+        let analysis = self.analysis(&root, &lines, started.elapsed());
+        self.tree = Some(tree);
+        analysis
     }
 
-    fn run_mcts(&self, board: &Board, stone: Stone) -> Option<(usize, usize)> {
-        // For the root, we don't have Ko information, so we use basic validation
-        let valid_moves = get_valid_moves(board, stone);
+    // Sums up the root children of a search, best first by the configured
+    // move selection, with placements ahead of a pass on a tie
+    fn analysis(&self, root: &MctsNode, lines: &[&MctsNode], elapsed: Duration) -> Analysis {
+        let stone = root.player_to_move;
+        let selection = self.config.move_selection;
+        // The lock is let go before the principal variations, which start
+        // from the root again
+        let (children, untried) = {
+            let expansion = root.expansion.lock().unwrap();
+            (expansion.children.clone(), expansion.untried_moves.clone())
+        };
+        let prior = 1.0 / (children.len() + untried.len()) as f64;
 
-        if valid_moves.is_empty() {
-            return None;
+        // Scores are taken before sorting, as other threads may still be
+        // changing the statistics
+        let mut scored: Vec<_> = children
+            .iter()
+            .filter(|edge| edge.node.visits() > 0)
+            .map(|edge| {
+                let candidate = CandidateInfo {
+                    mv: edge.mv,
+                    visits: edge.node.visits(),
+                    win_rate: edge.node.win_rate(stone),
+                    prior,
+                    pv: principal_variation(lines, edge.mv),
+                };
+                (selection.score(&edge.node, stone), candidate)
+            })
+            .collect();
+        // A search that ran out before any playout came back still has to
+        // play, and passing on an open board would lose the game
+        if scored.is_empty() {
+            let placement = children
+                .iter()
+                .map(|edge| edge.mv)
+                .chain(untried)
+                .find(|&mv| mv != Move::Pass);
+            if let Some(mv) = placement {
+                let candidate = CandidateInfo {
+                    mv,
+                    visits: 0,
+                    win_rate: 0.0,
+                    prior,
+                    pv: vec![mv],
+                };
+                scored.push((f64::NEG_INFINITY, candidate));
+            }
+        }
+        // Sorting up and reversing puts the last of equally good moves first
+        scored.sort_by(|(a_score, a), (b_score, b)| {
+            a_score
+                .total_cmp(b_score)
+                .then_with(|| (a.mv != Move::Pass).cmp(&(b.mv != Move::Pass)))
+        });
+        scored.reverse();
+
+        Analysis {
+            to_move: stone,
+            visits: root.visits(),
+            elapsed,
+            candidates: scored.into_iter().map(|(_, candidate)| candidate).collect(),
         }
+    }
 
-        if valid_moves.len() == 1 {
-            return Some(valid_moves[0]);
+    // A tree growing from `root`, with a table if transpositions are on
+    fn new_tree(&self, root: MctsNode) -> SearchTree {
+        match self.config.transpositions {
+            Some(capacity) => {
+                let table = TranspositionTable::new(capacity);
+                let key = root.key;
+                let root = table.find_or_create(key, true, || root).unwrap();
+                SearchTree {
+                    root,
+                    table: Some(table),
+                }
+            }
+            None => SearchTree {
+                root: Arc::new(root),
+                table: None,
+            },
         }
+    }
 
-        let root = Rc::new(RefCell::new(MctsNode::new(stone, None, valid_moves)));
-        let start_time = Instant::now();
-        let mut _iterations = 0;
+    // Runs iterations on `tree` until `countdown` is over, returning how
+    // many playouts were run. Safe to call from several threads at once.
+    fn search(
+        &self,
+        tree: &SearchTree,
+        board: &Board,
+        stone: Stone,
+        mut history: KoHistory,
+        mut countdown: Countdown,
+        rng: &mut StdRng,
+    ) -> u64 {
+        // Moves along the selected path are made on this board and taken
+        // back after each iteration instead of cloning the root position
+        let mut current_board = board.clone_position();
+        let root_history_len = history.len();
+        let mut playouts = 0;
+        // Moves of the current simulation and, per point, who played there
+        // first from a given depth on (for RAVE)
+        let mut played = Vec::new();
+        let mut first_player = vec![None; board.size() * board.size()];
 
-        while start_time.elapsed() < self.time_limit {
-            let mut current_board = board.clone();
-            let mut current_node = root.clone();
+        let table = tree.table.as_ref();
+        while !countdown.is_over() {
+            let mut current_node = tree.root.clone();
             let mut path = vec![current_node.clone()];
+            // moves[i] leads from path[i] to path[i + 1]
+            let mut moves = Vec::new();
             // Track whose turn it is to play from the current position
             let mut current_player = stone;
-            let mut board_history = vec![board.clone()];
+            // Stones placed below the root, to be taken back afterwards
+            let mut stones_placed = 0;
 
             // Selection phase - traverse tree using UCT
-            loop {
-                let node = current_node.borrow();
-
-                if !node.untried_moves.is_empty() || node.children.is_empty() {
-                    drop(node);
+            while let Some(child) =
+                current_node.select_child(self.config.exploration, self.config.rave)
+            {
+                // A ko cycle can lead a transposition back onto the path,
+                // so the search continues from here instead
+                if table.is_some() && path.iter().any(|node| Arc::ptr_eq(node, &child.node)) {
                     break;
                 }
-
-                if let Some(child) = node.select_child(self.exploration) {
-                    let child_move = child.borrow().move_pos.unwrap();
-                    let board_before_move = current_board.clone();
-                    // Play move for current player
-                    current_board
-                        .place_stone(child_move.0, child_move.1, current_player)
-                        .unwrap();
-                    board_history.push(board_before_move);
-                    // Now it's opponent's turn
-                    current_player = current_player.opposite();
-                    drop(node);
-                    current_node = child;
-                    path.push(current_node.clone());
-                } else {
-                    drop(node);
-                    break;
+                child.node.virtual_loss.fetch_add(1, Ordering::Relaxed);
+                // Play move for current player
+                let child_move = child.mv;
+                if let Move::Place(x, y) = child_move {
+                    current_board.make_move(x, y, current_player).unwrap();
+                    stones_placed += 1;
                 }
+                // Now it's opponent's turn
+                current_player = current_player.opposite();
+                match child_move {
+                    Move::Place(..) => history.push(&current_board, current_player),
+                    Move::Pass => history.push_pass(&current_board, current_player),
+                }
+                current_node = child.node;
+                path.push(current_node.clone());
+                moves.push(child_move);
             }
 
             // Expansion phase - add new child if possible
-            if let Some(new_child) = current_node
-                .borrow_mut()
-                .expand(&current_board, current_player)
-            {
-                let child_move = new_child.borrow().move_pos.unwrap();
-                current_board
-                    .place_stone(child_move.0, child_move.1, current_player)
-                    .unwrap();
+            let is_root = path.len() == 1;
+            if let Some(new_child) = current_node.expand(
+                &mut current_board,
+                current_player,
+                &history,
+                table,
+                is_root,
+                rng,
+            ) {
+                new_child.node.virtual_loss.fetch_add(1, Ordering::Relaxed);
+                if let Move::Place(x, y) = new_child.mv {
+                    current_board.make_move(x, y, current_player).unwrap();
+                    stones_placed += 1;
+                }
                 // After expansion, it's opponent's turn for simulation
                 current_player = current_player.opposite();
-                path.push(new_child);
+                path.push(new_child.node);
+                moves.push(new_child.mv);
             }
 
-            // Simulation phase - play out random game
+            // Simulation phase - play out random game. A game two passes
+            // already ended counts as a playout of no moves.
             // current_player is whose turn it is to play from current position
-            let simulation_result = self.simulate(&current_board, current_player);
+            played.clear();
+            let leaf = path.last().unwrap();
+            let simulation_result = if leaf.is_terminal() {
+                self.score(&current_board, current_player)
+            } else {
+                let last_move = moves.last().and_then(|mv: &Move| mv.to_option());
+                self.simulate(
+                    &current_board,
+                    current_player,
+                    leaf.key.passes,
+                    last_move,
+                    &mut played,
+                    rng,
+                )
+            };
+            countdown.playout_done();
+            playouts += 1;
 
             // Backpropagation phase
             // simulation_result is 1.0 if current_player wins, 0.0 if loses
@@ -287,56 +1244,96 @@ impl Mcts {
                 Stone::White => simulation_result < 0.5,
             };
 
-            // Update all nodes in the path
-            for node in path.iter() {
-                node.borrow_mut().update(black_won);
+            // Update all nodes in the path, releasing their virtual loss
+            for (depth, node) in path.iter().enumerate() {
+                node.update(black_won);
+                if depth > 0 {
+                    node.virtual_loss.fetch_sub(1, Ordering::Relaxed);
+                }
             }
 
-            _iterations += 1;
-        }
-
-        // Select best move based on visit count
-        let root_ref = root.borrow();
-        let best_child = root_ref
-            .children
-            .iter()
-            .max_by_key(|child| child.borrow().visits)
-            .cloned()?;
+            if self.config.rave != RaveSchedule::Off {
+                let board_size = board.size();
+                update_amaf(
+                    &path,
+                    &moves,
+                    &played,
+                    &mut first_player,
+                    board_size,
+                    black_won,
+                );
+            }
 
-        let best_move = best_child.borrow().move_pos;
+            for _ in 0..stones_placed {
+                current_board.unmake_move();
+            }
+            history.truncate(root_history_len);
 
-        // Debug output (commented out for production)
-        // println!("\nMCTS: {} iterations, root visits: {}", _iterations, root_ref.visits);
+            countdown.iteration_done();
+        }
 
-        best_move
+        playouts
     }
 }
 
-// Helper function to get valid moves considering eyes
-fn get_valid_moves(board: &Board, stone: Stone) -> Vec<(usize, usize)> {
-    let mut valid_moves = Vec::new();
-    let mut non_eye_moves = Vec::new();
+// Walks the path from the leaf up, marking the first color to play each
+// point below the current node, and credits the children of each node
+fn update_amaf(
+    path: &[Arc<MctsNode>],
+    moves: &[Move],
+    played: &[((usize, usize), Stone)],
+    first_player: &mut [Option<Stone>],
+    board_size: usize,
+    black_won: bool,
+) {
+    first_player.fill(None);
+    // Going backwards, earlier moves overwrite later ones at the same point
+    for &((x, y), stone) in played.iter().rev() {
+        first_player[y * board_size + x] = Some(stone);
+    }
 
-    for y in 0..board.size() {
-        for x in 0..board.size() {
-            if board.is_valid_move(x, y, stone) {
-                valid_moves.push((x, y));
-                if !board.is_eye(x, y, stone) {
-                    non_eye_moves.push((x, y));
-                }
-            }
+    for depth in (0..path.len()).rev() {
+        if let Some(&Move::Place(x, y)) = moves.get(depth) {
+            first_player[y * board_size + x] = Some(path[depth].player_to_move);
         }
+        path[depth].update_amaf(first_player, board_size, black_won);
     }
+}
 
-    // Count total eyes for our color
-    let total_eyes = board.count_eyes_for_color(stone);
+// The most visited line after `mv`, starting with it, followed through
+// whichever of the searched roots gave `mv` the most visits
+fn principal_variation(roots: &[&MctsNode], mv: Move) -> Vec<Move> {
+    let best_child = |node: &MctsNode, mv: Option<Move>| {
+        let expansion = node.expansion.lock().unwrap();
+        expansion
+            .children
+            .iter()
+            .filter(|edge| mv.is_none_or(|mv| edge.mv == mv) && edge.node.visits() > 0)
+            .max_by_key(|edge| edge.node.visits())
+            .cloned()
+    };
 
-    // If we have 2 or fewer eyes, only consider non-eye moves
-    if total_eyes <= 2 && !non_eye_moves.is_empty() {
-        non_eye_moves
-    } else {
-        valid_moves
+    let mut pv = Vec::new();
+    let mut next = roots
+        .iter()
+        .filter_map(|root| best_child(root, Some(mv)))
+        .max_by_key(|edge| edge.node.visits());
+    // Transpositions can lead back to an earlier node, so the length is capped
+    while let Some(edge) = next.filter(|_| pv.len() < MAX_PV_LENGTH) {
+        pv.push(edge.mv);
+        next = best_child(&edge.node, None);
     }
+    pv
+}
+
+// Tree actions from a position: the valid placements plus a pass
+fn candidate_moves(board: &Board, stone: Stone) -> Vec<Move> {
+    let mut moves: Vec<_> = get_valid_moves(board, stone)
+        .into_iter()
+        .map(|(x, y)| Move::Place(x, y))
+        .collect();
+    moves.push(Move::Pass);
+    moves
 }
 
 impl Player for Mcts {
@@ -344,7 +1341,25 @@ impl Player for Mcts {
         &self.name
     }
 
-    fn get_move(&self, board: &Board, stone: Stone) -> Option<(usize, usize)> {
-        self.run_mcts(board, stone)
+    fn get_move(&mut self, view: &GameView) -> Option<(usize, usize)> {
+        self.run_mcts(view, None).best_move().to_option()
+    }
+
+    fn analyze(
+        &mut self,
+        view: &GameView,
+        interval: Duration,
+        report: &mut dyn FnMut(&Analysis),
+    ) -> Option<Analysis> {
+        Some(self.run_mcts(view, Some((interval, report))))
+    }
+
+    fn new_game(&mut self, _board_size: usize, rules: &Rules) {
+        self.tree = None;
+        self.rules = *rules;
+    }
+
+    fn game_over(&mut self, _result: &GameResult) {
+        self.tree = None;
     }
 }
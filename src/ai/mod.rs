@@ -1,7 +1,11 @@
+pub mod budget;
 pub mod mc;
 pub mod mcts;
+pub mod playout;
 pub mod random;
 
-pub use mc::MonteCarloAI;
-pub use mcts::Mcts;
+pub use budget::SearchBudget;
+pub use mc::{MonteCarloAI, MoveAllocation};
+pub use mcts::{Mcts, MctsConfig, MoveSelection, Parallelism, RaveSchedule};
+pub use playout::{HeavyPolicy, LightPolicy, PlayoutPolicy, RandomPolicy};
 pub use random::RandomAI;
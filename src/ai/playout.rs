@@ -0,0 +1,383 @@
+use crate::ai::random;
+use crate::board::{Board, Stone};
+use rand::{Rng, RngCore};
+use std::sync::OnceLock;
+
+// Chooses the moves of the simulated games a search plays out. One policy
+// is shared by all the threads of a search, so it keeps no state between
+// calls; `last_move` is the placement just before this move, if any.
+pub trait PlayoutPolicy: Send + Sync {
+    fn name(&self) -> &str;
+
+    // The move `stone` plays next, None to pass. Ko is not checked.
+    fn pick_move(
+        &self,
+        board: &Board,
+        stone: Stone,
+        last_move: Option<(usize, usize)>,
+        rng: &mut dyn RngCore,
+    ) -> Option<(usize, usize)>;
+}
+
+// Uniformly random moves that do not fill the player's own eyes while it
+// has two or fewer, the playouts of the tree search
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LightPolicy;
+
+impl PlayoutPolicy for LightPolicy {
+    fn name(&self) -> &str {
+        "light"
+    }
+
+    fn pick_move(
+        &self,
+        board: &Board,
+        stone: Stone,
+        _last_move: Option<(usize, usize)>,
+        rng: &mut dyn RngCore,
+    ) -> Option<(usize, usize)> {
+        let valid_moves = get_valid_moves(board, stone);
+        if valid_moves.is_empty() {
+            None
+        } else {
+            Some(valid_moves[rng.gen_range(0..valid_moves.len())])
+        }
+    }
+}
+
+// The moves RandomAI plays, the playouts of the flat Monte Carlo search:
+// random moves found by a partial scan of the board, which fill an own eye
+// now and then while there are more than two and pass rather than fill
+// the last ones
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RandomPolicy;
+
+impl PlayoutPolicy for RandomPolicy {
+    fn name(&self) -> &str {
+        "random"
+    }
+
+    fn pick_move(
+        &self,
+        board: &Board,
+        stone: Stone,
+        _last_move: Option<(usize, usize)>,
+        mut rng: &mut dyn RngCore,
+    ) -> Option<(usize, usize)> {
+        random::pick_move(board, stone, &mut rng)
+    }
+}
+
+// Answers the last move before playing randomly: first by capturing
+// stones left in atari, then by saving its own stones put in atari, then
+// with a move next to it matching a 3x3 shape pattern (hane, cut and edge
+// shapes as in MoGo). Random moves avoid self-atari where they can.
+// Slower than the light policy, but its games look more like real ones.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HeavyPolicy;
+
+impl PlayoutPolicy for HeavyPolicy {
+    fn name(&self) -> &str {
+        "heavy"
+    }
+
+    fn pick_move(
+        &self,
+        board: &Board,
+        stone: Stone,
+        last_move: Option<(usize, usize)>,
+        rng: &mut dyn RngCore,
+    ) -> Option<(usize, usize)> {
+        if let Some(last) = last_move {
+            for candidates in [
+                captures(board, stone, last),
+                atari_escapes(board, stone, last),
+                pattern_moves(board, stone, last),
+            ] {
+                let playable: Vec<_> = candidates
+                    .into_iter()
+                    .filter(|&(x, y)| {
+                        board.is_valid_move(x, y, stone) && !board.is_eye(x, y, stone)
+                    })
+                    .collect();
+                if !playable.is_empty() {
+                    return Some(playable[rng.gen_range(0..playable.len())]);
+                }
+            }
+        }
+        // Otherwise a random move, giving a self-atari a couple more tries
+        // to find something better
+        let valid_moves = playout_moves(board, stone);
+        let mut mv = None;
+        for _ in 0..SELF_ATARI_RETRIES + 1 {
+            if valid_moves.is_empty() {
+                break;
+            }
+            let (x, y) = valid_moves[rng.gen_range(0..valid_moves.len())];
+            mv = Some((x, y));
+            if !is_self_atari(board, (x, y), stone) {
+                break;
+            }
+        }
+        mv
+    }
+}
+
+// Further random moves the heavy policy tries after drawing a self-atari
+const SELF_ATARI_RETRIES: usize = 2;
+
+// Whether a stone at `point` would be left with a single liberty without
+// capturing anything
+fn is_self_atari(board: &Board, point: (usize, usize), stone: Stone) -> bool {
+    !board.would_capture(point.0, point.1, stone) && liberties_after(board, point, stone) < 2
+}
+
+// The valid moves for `stone`, leaving out its own eyes while it has no
+// more than two of them
+pub(crate) fn get_valid_moves(board: &Board, stone: Stone) -> Vec<(usize, usize)> {
+    let mut valid_moves = Vec::new();
+    let mut non_eye_moves = Vec::new();
+
+    for y in 0..board.size() {
+        for x in 0..board.size() {
+            if board.is_valid_move(x, y, stone) {
+                valid_moves.push((x, y));
+                if !board.is_eye(x, y, stone) {
+                    non_eye_moves.push((x, y));
+                }
+            }
+        }
+    }
+
+    // Count total eyes for our color
+    let total_eyes = board.count_eyes_for_color(stone);
+
+    // If we have 2 or fewer eyes, only consider non-eye moves
+    if total_eyes <= 2 && !non_eye_moves.is_empty() {
+        non_eye_moves
+    } else {
+        valid_moves
+    }
+}
+
+// The moves a heavy playout picks from: those of `get_valid_moves`, but
+// none at all once only its own last eyes are left, so the player passes
+// rather than kill its living groups
+fn playout_moves(board: &Board, stone: Stone) -> Vec<(usize, usize)> {
+    let valid_moves = get_valid_moves(board, stone);
+    let fills_last_eye = valid_moves.iter().all(|&(x, y)| board.is_eye(x, y, stone))
+        && board.count_eyes_for_color(stone) <= 2;
+    if fills_last_eye {
+        Vec::new()
+    } else {
+        valid_moves
+    }
+}
+
+// Points that capture an opponent group in atari at or next to `last`
+fn captures(board: &Board, stone: Stone, last: (usize, usize)) -> Vec<(usize, usize)> {
+    let mut moves = Vec::new();
+    for (x, y) in std::iter::once(last).chain(neighbors(board, last)) {
+        if board.get(x, y) == Some(stone.opposite()) {
+            let liberties = board.liberties(x, y);
+            if liberties.len() == 1 {
+                moves.push(liberties[0]);
+            }
+        }
+    }
+    moves
+}
+
+// Extensions of own groups next to `last` that are in atari, when
+// extending gives them at least two liberties
+fn atari_escapes(board: &Board, stone: Stone, last: (usize, usize)) -> Vec<(usize, usize)> {
+    let mut moves = Vec::new();
+    for (x, y) in neighbors(board, last) {
+        if board.get(x, y) == Some(stone) {
+            let liberties = board.liberties(x, y);
+            if liberties.len() == 1 && liberties_after(board, liberties[0], stone) >= 2 {
+                moves.push(liberties[0]);
+            }
+        }
+    }
+    moves
+}
+
+// Empty points around `last` matching a shape pattern, unless playing
+// there would be a self-atari
+fn pattern_moves(board: &Board, stone: Stone, last: (usize, usize)) -> Vec<(usize, usize)> {
+    let (lx, ly) = (last.0 as isize, last.1 as isize);
+    let size = board.size() as isize;
+    let mut moves = Vec::new();
+    for (dx, dy) in SURROUNDING {
+        let (x, y) = (lx + dx, ly + dy);
+        if x < 0 || y < 0 || x >= size || y >= size {
+            continue;
+        }
+        let (x, y) = (x as usize, y as usize);
+        if board.get(x, y).is_none()
+            && pattern_table()[surrounding_code(board, x, y)]
+            && !is_self_atari(board, (x, y), stone)
+        {
+            moves.push((x, y));
+        }
+    }
+    moves
+}
+
+// Liberties a stone of `stone` at `point` would have, stones it captures
+// not taken into account
+fn liberties_after(board: &Board, point: (usize, usize), stone: Stone) -> usize {
+    let mut liberties = Vec::new();
+    for (x, y) in neighbors(board, point) {
+        let found = match board.get(x, y) {
+            None => vec![(x, y)],
+            Some(owner) if owner == stone => board.liberties(x, y),
+            Some(_) => Vec::new(),
+        };
+        for liberty in found {
+            if liberty != point && !liberties.contains(&liberty) {
+                liberties.push(liberty);
+            }
+        }
+    }
+    liberties.len()
+}
+
+fn neighbors(board: &Board, (x, y): (usize, usize)) -> impl Iterator<Item = (usize, usize)> {
+    let size = board.size();
+    [
+        (x.wrapping_sub(1), y),
+        (x + 1, y),
+        (x, y.wrapping_sub(1)),
+        (x, y + 1),
+    ]
+    .into_iter()
+    .filter(move |&(x, y)| x < size && y < size)
+}
+
+// The eight points around a point, row by row, as the patterns list them
+const SURROUNDING: [(isize, isize); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+// Point contents in a pattern code, two bits per surrounding point
+const EMPTY: usize = 0;
+const BLACK: usize = 1;
+const WHITE: usize = 2;
+const EDGE: usize = 3;
+
+// Shapes around an empty centre point, from Michi's take on the MoGo
+// patterns. X and O are the two colors (either may be to move), x and o
+// anything but X and O, # off the board and ? anything at all.
+const PATTERNS: [[&str; 3]; 13] = [
+    ["XOX", "...", "???"], // Hane, enclosing
+    ["XO.", "...", "?.?"], // Hane, non-cutting
+    ["XO?", "X..", "x.?"], // Hane, magari
+    [".O.", "X..", "..."], // Katatsuke or diagonal attachment
+    ["XO?", "O.o", "?o?"], // Cut, unprotected
+    ["XO?", "O.X", "???"], // Cut, peeped
+    ["?X?", "O.O", "ooo"], // Cut, de
+    ["OX?", "o.O", "???"], // Cut, keima
+    ["X.?", "O.?", "###"], // Edge, chase
+    ["OX?", "X.O", "###"], // Edge, block side cut
+    ["?X?", "x.O", "###"], // Edge, block side connection
+    ["?XO", "x.x", "###"], // Edge, sagari
+    ["?OX", "X.O", "###"], // Edge, cut
+];
+
+fn surrounding_code(board: &Board, x: usize, y: usize) -> usize {
+    let size = board.size() as isize;
+    SURROUNDING
+        .iter()
+        .enumerate()
+        .map(|(i, &(dx, dy))| {
+            let (nx, ny) = (x as isize + dx, y as isize + dy);
+            let content = if nx < 0 || ny < 0 || nx >= size || ny >= size {
+                EDGE
+            } else {
+                match board.get(nx as usize, ny as usize) {
+                    None => EMPTY,
+                    Some(Stone::Black) => BLACK,
+                    Some(Stone::White) => WHITE,
+                }
+            };
+            content << (2 * i)
+        })
+        .sum()
+}
+
+// Whether each code of the eight surrounding points matches a pattern in
+// any rotation, reflection or color swap, built on first use
+fn pattern_table() -> &'static [bool] {
+    static TABLE: OnceLock<Vec<bool>> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let mut table = vec![false; 1 << 16];
+        for pattern in &PATTERNS {
+            for symmetry in 0..8 {
+                for swap in [false, true] {
+                    // The contents each surrounding point may have, as bits
+                    let allowed = SURROUNDING.map(|(dx, dy)| {
+                        let (px, py) = transform(dx, dy, symmetry);
+                        let cell = pattern[(py + 1) as usize].as_bytes()[(px + 1) as usize];
+                        allowed_contents(cell, swap)
+                    });
+                    mark_matches(&allowed, 0, 0, &mut table);
+                }
+            }
+        }
+        table
+    })
+}
+
+fn transform(dx: isize, dy: isize, symmetry: usize) -> (isize, isize) {
+    let (dx, dy) = if symmetry & 1 != 0 {
+        (-dx, dy)
+    } else {
+        (dx, dy)
+    };
+    let (dx, dy) = if symmetry & 2 != 0 {
+        (dx, -dy)
+    } else {
+        (dx, dy)
+    };
+    if symmetry & 4 != 0 {
+        (dy, dx)
+    } else {
+        (dx, dy)
+    }
+}
+
+fn allowed_contents(cell: u8, swap: bool) -> u8 {
+    let (x, o) = if swap { (WHITE, BLACK) } else { (BLACK, WHITE) };
+    let any = (1 << EMPTY) | (1 << BLACK) | (1 << WHITE) | (1 << EDGE);
+    match cell {
+        b'X' => 1 << x,
+        b'O' => 1 << o,
+        b'.' => 1 << EMPTY,
+        b'#' => 1 << EDGE,
+        b'x' => any & !(1 << x),
+        b'o' => any & !(1 << o),
+        _ => any,
+    }
+}
+
+// Marks every code whose points from `point` on have allowed contents
+fn mark_matches(allowed: &[u8; 8], point: usize, code: usize, table: &mut [bool]) {
+    if point == allowed.len() {
+        table[code] = true;
+        return;
+    }
+    for content in [EMPTY, BLACK, WHITE, EDGE] {
+        if allowed[point] & (1 << content) != 0 {
+            mark_matches(allowed, point + 1, code | content << (2 * point), table);
+        }
+    }
+}
@@ -1,17 +1,29 @@
 use crate::board::{Board, Stone};
+use crate::game::Move;
 use crate::player::Player;
-use rand::{thread_rng, Rng};
+use crate::view::GameView;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
 
 pub struct RandomAI {
     name: String,
+    rng: StdRng,
 }
 
 impl RandomAI {
     pub fn new() -> Self {
         RandomAI {
             name: "Random AI".to_string(),
+            rng: StdRng::from_entropy(),
         }
     }
+
+    // Makes the moves picked reproducible
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
 }
 
 impl Default for RandomAI {
@@ -25,81 +37,99 @@ impl Player for RandomAI {
         &self.name
     }
 
-    fn get_move(&self, board: &Board, stone: Stone) -> Option<(usize, usize)> {
-        let size = board.size();
-        let mut valid_moves = Vec::with_capacity(size * size);
-        let mut non_eye_moves = Vec::with_capacity(20); // Usually don't need more
-
-        // Early exit constants
-        const MIN_NON_EYE_MOVES: usize = 10;
-        const MAX_SCAN_POSITIONS: usize = 50;
-        let mut scanned = 0;
-
-        // Scan board with early exit
-        'outer: for y in 0..size {
-            for x in 0..size {
-                if board.is_valid_move(x, y, stone) {
-                    valid_moves.push((x, y));
-
-                    if !board.is_eye(x, y, stone) {
-                        non_eye_moves.push((x, y));
-
-                        // Early exit if we have enough non-eye moves
-                        if non_eye_moves.len() >= MIN_NON_EYE_MOVES {
-                            break 'outer;
-                        }
+    fn get_move(&mut self, view: &GameView) -> Option<(usize, usize)> {
+        let stone = view.to_move;
+        match pick_move(view.board, stone, &mut self.rng) {
+            Some((x, y)) if !view.is_legal(Move::Place(x, y)) => {
+                // The quick scan found a move the ko rule forbids, so pick
+                // among the moves that are actually legal
+                let size = view.board.size();
+                let candidates: Vec<_> = (0..size)
+                    .flat_map(|y| (0..size).map(move |x| (x, y)))
+                    .filter(|&(x, y)| {
+                        view.is_legal(Move::Place(x, y)) && !view.board.is_eye(x, y, stone)
+                    })
+                    .collect();
+                candidates.choose(&mut self.rng).copied()
+            }
+            mv => mv,
+        }
+    }
+}
+
+// Random move for `stone` that avoids filling its own eyes, ignoring ko
+pub(crate) fn pick_move(board: &Board, stone: Stone, rng: &mut impl Rng) -> Option<(usize, usize)> {
+    let size = board.size();
+    let mut valid_moves = Vec::with_capacity(size * size);
+    let mut non_eye_moves = Vec::with_capacity(20); // Usually don't need more
+
+    // Early exit constants
+    const MIN_NON_EYE_MOVES: usize = 10;
+    const MAX_SCAN_POSITIONS: usize = 50;
+    let mut scanned = 0;
+
+    // Scan board with early exit
+    'outer: for y in 0..size {
+        for x in 0..size {
+            if board.is_valid_move(x, y, stone) {
+                valid_moves.push((x, y));
+
+                if !board.is_eye(x, y, stone) {
+                    non_eye_moves.push((x, y));
+
+                    // Early exit if we have enough non-eye moves
+                    if non_eye_moves.len() >= MIN_NON_EYE_MOVES {
+                        break 'outer;
                     }
                 }
+            }
 
-                scanned += 1;
-                // Limit total positions scanned in midgame/endgame
-                if scanned >= MAX_SCAN_POSITIONS && !non_eye_moves.is_empty() {
-                    break 'outer;
-                }
+            scanned += 1;
+            // Limit total positions scanned in midgame/endgame
+            if scanned >= MAX_SCAN_POSITIONS && !non_eye_moves.is_empty() {
+                break 'outer;
             }
         }
+    }
 
-        // If there are no valid moves at all, pass
-        if valid_moves.is_empty() {
-            return None;
-        }
+    // If there are no valid moves at all, pass
+    if valid_moves.is_empty() {
+        return None;
+    }
 
-        let mut rng = thread_rng();
-
-        // If we have non-eye moves, prefer them
-        if !non_eye_moves.is_empty() {
-            // Only count eyes if we might need to fill them
-            if non_eye_moves.len() < 3 {
-                let total_eyes = board.count_eyes_for_color(stone);
-
-                if total_eyes > 2 {
-                    // 80% chance to play non-eye move, 20% to fill an eye
-                    if rng.gen_bool(0.8) || non_eye_moves.is_empty() {
-                        let index = rng.gen_range(0..non_eye_moves.len());
-                        return Some(non_eye_moves[index]);
-                    } else {
-                        let index = rng.gen_range(0..valid_moves.len());
-                        return Some(valid_moves[index]);
-                    }
-                } else {
-                    // Don't fill eyes if we have 2 or fewer
+    // If we have non-eye moves, prefer them
+    if !non_eye_moves.is_empty() {
+        // Only count eyes if we might need to fill them
+        if non_eye_moves.len() < 3 {
+            let total_eyes = board.count_eyes_for_color(stone);
+
+            if total_eyes > 2 {
+                // 80% chance to play non-eye move, 20% to fill an eye
+                if rng.gen_bool(0.8) || non_eye_moves.is_empty() {
                     let index = rng.gen_range(0..non_eye_moves.len());
                     return Some(non_eye_moves[index]);
+                } else {
+                    let index = rng.gen_range(0..valid_moves.len());
+                    return Some(valid_moves[index]);
                 }
             } else {
-                // We have plenty of non-eye moves, just pick one
+                // Don't fill eyes if we have 2 or fewer
                 let index = rng.gen_range(0..non_eye_moves.len());
                 return Some(non_eye_moves[index]);
             }
-        }
-
-        // If we only have eye moves, check if we should fill them
-        let total_eyes = board.count_eyes_for_color(stone);
-        if total_eyes > 2 {
-            let index = rng.gen_range(0..valid_moves.len());
-            Some(valid_moves[index])
         } else {
-            None // Pass to preserve eyes
+            // We have plenty of non-eye moves, just pick one
+            let index = rng.gen_range(0..non_eye_moves.len());
+            return Some(non_eye_moves[index]);
         }
     }
+
+    // If we only have eye moves, check if we should fill them
+    let total_eyes = board.count_eyes_for_color(stone);
+    if total_eyes > 2 {
+        let index = rng.gen_range(0..valid_moves.len());
+        Some(valid_moves[index])
+    } else {
+        None // Pass to preserve eyes
+    }
 }
@@ -0,0 +1,36 @@
+use crate::board::Stone;
+use crate::game::Move;
+use std::time::Duration;
+
+// One move a search looked at
+#[derive(Debug, Clone, PartialEq)]
+pub struct CandidateInfo {
+    pub mv: Move,
+    pub visits: u32,
+    pub win_rate: f64, // For the side to move, between 0 and 1
+    // Share of the search the move was given before any result came in.
+    // None of the engines has a move policy, so this is uniform.
+    pub prior: f64,
+    pub pv: Vec<Move>, // Principal variation, starting with `mv`
+}
+
+// What a search has found so far. The candidates are ordered best first,
+// so the first one is the move the player picks; none means a pass.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Analysis {
+    pub to_move: Stone,
+    pub visits: u32, // Simulations behind the candidates, earlier searches included
+    pub elapsed: Duration,
+    pub candidates: Vec<CandidateInfo>,
+}
+
+// How often a search being analysed reports, and where to
+pub(crate) type Reporter<'a> = (Duration, &'a mut dyn FnMut(&Analysis));
+
+impl Analysis {
+    pub fn best_move(&self) -> Move {
+        self.candidates
+            .first()
+            .map_or(Move::Pass, |candidate| candidate.mv)
+    }
+}
@@ -1,9 +1,50 @@
-use jungo::ai::{Mcts, MonteCarloAI, RandomAI};
+use jungo::ai::{
+    HeavyPolicy, Mcts, MctsConfig, MonteCarloAI, MoveAllocation, Parallelism, RandomAI,
+    RaveSchedule, SearchBudget,
+};
+use jungo::export::{write_match_summary, write_stats_summary, RecordFormat, RecordWriter};
 use jungo::game::Game;
+use jungo::gtp::GtpEngine;
 use jungo::player::{HumanPlayer, Player};
-use std::io::{self, Write};
+use jungo::rating::Ratings;
+use jungo::rules::Rules;
+use jungo::sgf::SgfRecord;
+use jungo::stats::{run_sprt, run_statistics_with, GameRecord, Sprt, SprtDecision};
+use jungo::tournament::{Format, Tournament};
+use std::env;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::process;
+use std::thread;
 
 fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.iter().any(|a| a == "--gtp") {
+        run_gtp(&args);
+        return;
+    }
+    if args.iter().any(|a| a == "--stats") {
+        run_stats(&args);
+        return;
+    }
+    if args.iter().any(|a| a == "--match") {
+        run_tournament(&args);
+        return;
+    }
+
+    let mut sgf_path = None;
+    let mut rules = Rules::default();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--sgf" => sgf_path = Some(expect_value(&mut iter, arg)),
+            "--rules" => rules.scoring = parse_value(&mut iter, arg),
+            "--komi" => rules.komi = parse_value(&mut iter, arg),
+            "--ko" => rules.ko = parse_value(&mut iter, arg),
+            _ => usage_error(&format!("unknown option '{}'", arg)),
+        }
+    }
+
     println!("純碁 (Jungo) - A simple Go variant");
     println!();
 
@@ -11,15 +52,431 @@ fn main() {
     let board_size = select_board_size();
 
     // Select game mode
-    let (player1, player2): (Box<dyn Player>, Box<dyn Player>) = match select_game_mode() {
+    let (mut player1, mut player2): (Box<dyn Player>, Box<dyn Player>) = match select_game_mode() {
         1 => (Box::new(HumanPlayer::new()), select_ai_player()),
         2 => (select_ai_player(), Box::new(HumanPlayer::new())),
         3 => (select_ai_player(), select_ai_player()),
         _ => unreachable!(),
     };
 
-    let mut game = Game::new(board_size);
-    game.play(player1.as_ref(), player2.as_ref());
+    let mut game = Game::with_rules(board_size, rules);
+    game.play(player1.as_mut(), player2.as_mut());
+
+    if let Some(path) = sgf_path {
+        let record = SgfRecord::from_game(&game, player1.name(), player2.name());
+        match record.save(&path) {
+            Ok(()) => println!("Game record saved to {}", path),
+            Err(e) => eprintln!("Failed to save game record to {}: {}", path, e),
+        }
+    }
+}
+
+// How the engines of the GTP and match modes search
+#[derive(Clone, Copy)]
+struct EngineOptions {
+    budget: SearchBudget,
+    threads: usize,
+    parallelism: Parallelism,
+    config: MctsConfig,
+    heavy_playouts: bool,
+    allocation: MoveAllocation,
+}
+
+impl Default for EngineOptions {
+    fn default() -> Self {
+        EngineOptions {
+            budget: SearchBudget::from_millis(1000),
+            threads: 1,
+            parallelism: Parallelism::Tree,
+            config: MctsConfig::default(),
+            heavy_playouts: false,
+            allocation: MoveAllocation::default(),
+        }
+    }
+}
+
+impl EngineOptions {
+    // Takes `arg` and its value if it is an engine option, returning
+    // whether it was one
+    fn parse(&mut self, arg: &str, iter: &mut std::slice::Iter<String>) -> bool {
+        match arg {
+            "--threads" => self.threads = parse_value(iter, arg),
+            "--root-parallel" => self.parallelism = Parallelism::Root,
+            "--no-rave" => self.config = self.config.with_rave(RaveSchedule::Off),
+            "--transpositions" => {
+                self.config = self.config.with_transpositions(parse_value(iter, arg))
+            }
+            "--heavy-playouts" => self.heavy_playouts = true,
+            "--allocation" => self.allocation = parse_value(iter, arg),
+            "--time-ms" => self.budget = SearchBudget::from_millis(parse_value(iter, arg)),
+            "--playouts" => self.budget = SearchBudget::Playouts(parse_value(iter, arg)),
+            _ => return false,
+        }
+        true
+    }
+
+    fn build(&self, engine: &str, seed: u64) -> Box<dyn Player> {
+        match engine {
+            "random" => Box::new(RandomAI::new().with_seed(seed)),
+            "mc" => {
+                let mc = MonteCarloAI::new_with_budget(self.budget)
+                    .with_allocation(self.allocation)
+                    .with_seed(seed);
+                if self.heavy_playouts {
+                    Box::new(mc.with_playout_policy(HeavyPolicy))
+                } else {
+                    Box::new(mc)
+                }
+            }
+            "mcts" => {
+                let mcts = Mcts::new_with_budget(self.budget)
+                    .with_threads(self.threads)
+                    .with_parallelism(self.parallelism)
+                    .with_config(self.config)
+                    .with_seed(seed);
+                if self.heavy_playouts {
+                    Box::new(mcts.with_playout_policy(HeavyPolicy))
+                } else {
+                    Box::new(mcts)
+                }
+            }
+            _ => usage_error(&format!("unknown engine '{}'", engine)),
+        }
+    }
+}
+
+fn run_gtp(args: &[String]) {
+    let mut engine = "mcts".to_string();
+    let mut options = EngineOptions::default();
+    let mut seed = None;
+    let mut board_size = 9;
+    let mut rules = Rules::default();
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if options.parse(arg, &mut iter) {
+            continue;
+        }
+        match arg.as_str() {
+            "--gtp" => {}
+            "--rules" => rules.scoring = parse_value(&mut iter, arg),
+            "--komi" => rules.komi = parse_value(&mut iter, arg),
+            "--ko" => rules.ko = parse_value(&mut iter, arg),
+            "--engine" => engine = expect_value(&mut iter, arg),
+            "--seed" => seed = Some(parse_value(&mut iter, arg)),
+            "--size" => board_size = parse_value(&mut iter, arg),
+            _ => usage_error(&format!("unknown option '{}'", arg)),
+        }
+    }
+
+    // Without a seed every engine starts from a random one
+    let seed = seed.unwrap_or_else(rand::random);
+    let player = options.build(&engine, seed);
+
+    let mut gtp = GtpEngine::with_rules(board_size, rules, player);
+    let stdin = io::stdin();
+    if let Err(e) = gtp.run(stdin.lock(), io::stdout()) {
+        eprintln!("GTP I/O error: {}", e);
+        process::exit(1);
+    }
+}
+
+// Plays games between RandomAIs and prints their statistics. Status lines
+// go to stderr, so stdout only carries the summary.
+fn run_stats(args: &[String]) {
+    let mut board_size = 9;
+    let mut games = 1000;
+    let mut threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let mut seed = None;
+    let mut rules = Rules::default();
+    let mut records_path = None;
+    let mut format = RecordFormat::default();
+    let mut summary_path = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--stats" => {}
+            "--size" => board_size = parse_value(&mut iter, arg),
+            "--games" => games = parse_value(&mut iter, arg),
+            "--threads" => threads = parse_value(&mut iter, arg),
+            "--seed" => seed = Some(parse_value(&mut iter, arg)),
+            "--rules" => rules.scoring = parse_value(&mut iter, arg),
+            "--komi" => rules.komi = parse_value(&mut iter, arg),
+            "--ko" => rules.ko = parse_value(&mut iter, arg),
+            "--records" => records_path = Some(expect_value(&mut iter, arg)),
+            "--summary" => summary_path = Some(expect_value(&mut iter, arg)),
+            "--format" => format = parse_value(&mut iter, arg),
+            _ => usage_error(&format!("unknown option '{}'", arg)),
+        }
+    }
+
+    let mut records = Vec::new();
+    eprintln!(
+        "Running {} games on {}x{} board...",
+        games, board_size, board_size
+    );
+    let stats = run_statistics_with(
+        board_size,
+        games,
+        rules,
+        threads,
+        seed.unwrap_or_else(rand::random),
+        &mut |record| {
+            records.push(record.clone());
+            if records.len().is_multiple_of(1000) && records.len() < games as usize {
+                eprint!(
+                    "Progress: {}/{} games ({:.1}%)...\r",
+                    records.len(),
+                    games,
+                    (records.len() as f64 / games as f64) * 100.0
+                );
+            }
+        },
+    );
+    eprintln!("\nCompleted {} games!", games);
+    stats.print_summary(games, board_size);
+
+    if let Some(path) = records_path {
+        // Games arrive as the threads finish them
+        records.sort_by_key(|record| record.game);
+        write_records(&path, format, &records);
+    }
+    if let Some(path) = summary_path {
+        write_summary(&path, |file| {
+            write_stats_summary(file, format, &stats, board_size)
+        });
+    }
+}
+
+// Plays a round robin (or gauntlet) between engines and prints the
+// cross-table and ratings anchored on the first engine, or with --sprt
+// tests the first of two engines against the second
+fn run_tournament(args: &[String]) {
+    let mut engines = Vec::new();
+    let mut options = EngineOptions::default();
+    let mut format = Format::RoundRobin;
+    let mut games = None;
+    let mut sprt = None;
+    let mut board_size = 9;
+    let mut max_moves = None;
+    let mut seed = None;
+    let mut rules = Rules::default();
+    let mut records_path = None;
+    let mut record_format = RecordFormat::default();
+    let mut summary_path = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if options.parse(arg, &mut iter) {
+            continue;
+        }
+        match arg.as_str() {
+            "--match" => {
+                let list = expect_value(&mut iter, arg);
+                engines = list.split(',').map(str::to_string).collect();
+            }
+            "--gauntlet" => format = Format::Gauntlet,
+            "--games" => games = Some(parse_value(&mut iter, arg)),
+            "--sprt" => {
+                let elo0 = parse_value(&mut iter, arg);
+                let elo1 = parse_value(&mut iter, arg);
+                sprt = Some(Sprt::new(elo0, elo1).unwrap_or_else(|e| usage_error(&e)));
+            }
+            "--size" => board_size = parse_value(&mut iter, arg),
+            "--max-moves" => max_moves = Some(parse_value(&mut iter, arg)),
+            "--seed" => seed = Some(parse_value(&mut iter, arg)),
+            "--rules" => rules.scoring = parse_value(&mut iter, arg),
+            "--komi" => rules.komi = parse_value(&mut iter, arg),
+            "--ko" => rules.ko = parse_value(&mut iter, arg),
+            "--records" => records_path = Some(expect_value(&mut iter, arg)),
+            "--summary" => summary_path = Some(expect_value(&mut iter, arg)),
+            "--format" => record_format = parse_value(&mut iter, arg),
+            _ => usage_error(&format!("unknown option '{}'", arg)),
+        }
+    }
+    if engines.len() < 2 {
+        usage_error("--match needs at least two engines");
+    }
+
+    if let Some(mut sprt) = sprt {
+        if engines.len() != 2 {
+            usage_error("--sprt needs exactly two engines");
+        }
+        // --games caps the test, which otherwise runs until it decides
+        if let Some(games) = games {
+            sprt = sprt.with_max_games(games);
+        }
+        let seed = seed.unwrap_or_else(rand::random);
+        let mut player = options.build(&engines[0], seed);
+        let mut opponent = options.build(&engines[1], seed.wrapping_add(1));
+        let (lower, upper) = sprt.bounds();
+        eprintln!(
+            "SPRT of {} against {} on {}x{} board, H0 {} Elo, H1 {} Elo...",
+            player.name(),
+            opponent.name(),
+            board_size,
+            board_size,
+            sprt.elo0,
+            sprt.elo1
+        );
+
+        let mut records = Vec::new();
+        let outcome = run_sprt(
+            player.as_mut(),
+            opponent.as_mut(),
+            board_size,
+            sprt,
+            rules,
+            max_moves,
+            &mut |record, stats, llr| {
+                records.push(record.clone());
+                eprintln!(
+                    "Game {}: {}-{}-{}, LLR {:.2} ({:.2}, {:.2})",
+                    record.game + 1,
+                    stats.wins,
+                    stats.losses,
+                    stats.draws,
+                    llr,
+                    lower,
+                    upper
+                );
+            },
+        );
+        let decision = match outcome.decision {
+            Some(SprtDecision::AcceptH1) => "H1 accepted",
+            Some(SprtDecision::AcceptH0) => "H0 accepted",
+            None => "no decision",
+        };
+        println!(
+            "{} vs {}: {}-{}-{} in {} games",
+            player.name(),
+            opponent.name(),
+            outcome.stats.wins,
+            outcome.stats.losses,
+            outcome.stats.draws,
+            outcome.stats.games()
+        );
+        println!(
+            "LLR {:.2} ({:.2}, {:.2}): {}",
+            outcome.llr, lower, upper, decision
+        );
+
+        if let Some(path) = records_path {
+            write_records(&path, record_format, &records);
+        }
+        if let Some(path) = summary_path {
+            let matches = [(player.name(), opponent.name(), outcome.stats)];
+            write_summary(&path, |file| {
+                write_match_summary(file, record_format, &matches)
+            });
+        }
+        return;
+    }
+
+    let games = games.unwrap_or(2);
+    let mut tournament = Tournament::new(format)
+        .with_games(games)
+        .with_board_sizes(&[board_size])
+        .with_rules(rules);
+    if let Some(moves) = max_moves {
+        tournament = tournament.with_max_moves(moves);
+    }
+    for (i, engine) in engines.iter().enumerate() {
+        // Entrants are numbered, as the same engine may play itself
+        let name = format!("{}. {}", i + 1, engine);
+        let engine = engine.clone();
+        // Without a seed every match starts from a random one
+        let entrant_seed = seed.map(|seed: u64| seed.wrapping_add(i as u64));
+        tournament = tournament.with_entrant(name, move || {
+            options.build(&engine, entrant_seed.unwrap_or_else(rand::random))
+        });
+    }
+
+    eprintln!(
+        "Playing {} games per pairing on {}x{} board...",
+        games, board_size, board_size
+    );
+    let (table, records) = tournament.run_recorded();
+    println!("{}", table);
+    print!("{}", Ratings::estimate(&table, 0));
+
+    if let Some(path) = records_path {
+        write_records(&path, record_format, &records);
+    }
+    if let Some(path) = summary_path {
+        // Every pairing once, from the side of the entrant listed first
+        let names = table.names();
+        let mut matches = Vec::new();
+        for a in 0..names.len() {
+            for b in a + 1..names.len() {
+                if let Some(stats) = table.result(a, b) {
+                    matches.push((names[a].as_str(), names[b].as_str(), stats));
+                }
+            }
+        }
+        write_summary(&path, |file| {
+            write_match_summary(file, record_format, &matches)
+        });
+    }
+}
+
+fn write_records(path: &str, format: RecordFormat, records: &[GameRecord]) {
+    let result = File::create(path).and_then(|file| {
+        let mut writer = RecordWriter::new(BufWriter::new(file), format);
+        writer.write_all(records)?;
+        writer.into_inner().flush()
+    });
+    match result {
+        Ok(()) => eprintln!("{} game records saved to {}", records.len(), path),
+        Err(e) => {
+            eprintln!("Failed to write game records to {}: {}", path, e);
+            process::exit(1);
+        }
+    }
+}
+
+fn write_summary(path: &str, write: impl FnOnce(File) -> io::Result<()>) {
+    if let Err(e) = File::create(path).and_then(write) {
+        eprintln!("Failed to write summary to {}: {}", path, e);
+        process::exit(1);
+    }
+}
+
+fn expect_value(iter: &mut std::slice::Iter<String>, option: &str) -> String {
+    match iter.next() {
+        Some(value) => value.clone(),
+        None => usage_error(&format!("missing value for '{}'", option)),
+    }
+}
+
+fn parse_value<T: std::str::FromStr>(iter: &mut std::slice::Iter<String>, option: &str) -> T {
+    let value = expect_value(iter, option);
+    match value.parse() {
+        Ok(v) => v,
+        Err(_) => usage_error(&format!("invalid value '{}' for '{}'", value, option)),
+    }
+}
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("error: {}", message);
+    eprintln!("usage: jungo [--sgf FILE] [RULE OPTIONS]");
+    eprintln!(
+        "       jungo --gtp [--engine random|mc|mcts] [--time-ms N] [--size N] [RULE OPTIONS]"
+    );
+    eprintln!("                   [--playouts N] [--seed N] [--threads N] [--root-parallel]");
+    eprintln!("                   [--no-rave] [--transpositions MAX_NODES] [--heavy-playouts]");
+    eprintln!("                   [--allocation round-robin|ucb1|halving]");
+    eprintln!("       jungo --stats [--size N] [--games N] [--threads N] [--seed N]");
+    eprintln!("                     [RULE OPTIONS] [RECORD OPTIONS] [--summary FILE]");
+    eprintln!("       jungo --match ENGINE,ENGINE[,...] [--gauntlet] [--games N] [--size N]");
+    eprintln!("                     [--max-moves N] [--seed N] [ENGINE OPTIONS] [RULE OPTIONS]");
+    eprintln!("                     [--sprt ELO0 ELO1] [RECORD OPTIONS] [--summary FILE]");
+    eprintln!("engines: random|mc|mcts, with the search options of --gtp");
+    eprintln!("rule options: --rules stones+captures|stones|area|territory --komi K");
+    eprintln!("              --ko simple|positional|situational");
+    eprintln!("record options: --records FILE --format csv|jsonl");
+    process::exit(2);
 }
 
 fn select_board_size() -> usize {
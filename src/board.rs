@@ -25,14 +25,28 @@ impl fmt::Display for Stone {
     }
 }
 
+// Everything make_move changed, so unmake_move can restore it. Captured
+// points live in Board::undo_points to keep records Copy and clones cheap.
+#[derive(Debug, Clone, Copy)]
+struct UndoRecord {
+    index: usize,
+    stone: Stone,
+    removed_start: usize, // Offset of the removed stones in undo_points
+    captured: usize,      // Opponent stones removed, also the capture count delta
+    self_captured: usize, // Own stones removed by self-capture
+    hash_delta: u64,
+}
+
 // Fast board using flat array and u8 representation
 #[derive(Debug, Clone)]
 pub struct Board {
     size: usize,
-    grid: Vec<u8>,            // 0 = empty, 1 = black, 2 = white
-    captured: (usize, usize), // (black_captured, white_captured)
-    zobrist_table: ZobristTable,
+    grid: Vec<u8>,                        // 0 = empty, 1 = black, 2 = white
+    captured: (usize, usize),             // (black_captured, white_captured)
+    zobrist_table: &'static ZobristTable, // Shared per board size, so clones stay cheap
     current_hash: u64,
+    undo_stack: Vec<UndoRecord>,
+    undo_points: Vec<usize>,
 }
 
 const EMPTY: u8 = 0;
@@ -45,8 +59,10 @@ impl Board {
             size,
             grid: vec![EMPTY; size * size],
             captured: (0, 0),
-            zobrist_table: ZobristTable::new(size),
+            zobrist_table: ZobristTable::for_size(size),
             current_hash: 0,
+            undo_stack: Vec::new(),
+            undo_points: Vec::new(),
         }
     }
 
@@ -133,6 +149,29 @@ impl Board {
         false // Would be suicide without capture
     }
 
+    // Whether playing at the empty point (x, y) would capture opponent stones
+    pub fn would_capture(&self, x: usize, y: usize, stone: Stone) -> bool {
+        let opponent_u8 = Self::opposite_u8(Self::stone_to_u8(stone));
+        let (neighbors, neighbor_count) = self.get_neighbors_array(x, y);
+        neighbors[..neighbor_count].iter().any(|&(nx, ny)| {
+            self.get_raw(nx, ny) == opponent_u8 && self.would_capture_after_move(nx, ny, x, y)
+        })
+    }
+
+    // The empty points next to the group at (x, y), each listed once
+    pub fn liberties(&self, x: usize, y: usize) -> Vec<(usize, usize)> {
+        let mut liberties = Vec::new();
+        for (gx, gy) in self.get_group(x, y) {
+            let (neighbors, neighbor_count) = self.get_neighbors_array(gx, gy);
+            for &(nx, ny) in &neighbors[..neighbor_count] {
+                if self.get_raw(nx, ny) == EMPTY && !liberties.contains(&(nx, ny)) {
+                    liberties.push((nx, ny));
+                }
+            }
+        }
+        liberties
+    }
+
     // Helper method: check if a group would be captured after blocking one liberty
     fn would_capture_after_move(
         &self,
@@ -207,6 +246,9 @@ impl Board {
         false
     }
 
+    // Plays a stone without recording it for unmake_move. Taking back
+    // earlier moves past it would corrupt the board, so it also clears the
+    // undo stack.
     pub fn place_stone(&mut self, x: usize, y: usize, stone: Stone) -> Result<(), &'static str> {
         if x >= self.size || y >= self.size {
             return Err("Position out of bounds");
@@ -216,6 +258,9 @@ impl Board {
             return Err("Position already occupied");
         }
 
+        self.undo_stack.clear();
+        self.undo_points.clear();
+
         let stone_u8 = Self::stone_to_u8(stone);
         let idx = self.index(x, y);
         self.grid[idx] = stone_u8;
@@ -226,7 +271,7 @@ impl Board {
             .get_stone_hash(x, y, stone == Stone::Black);
 
         // Check and remove captured stones
-        let captured = self.check_captures(x, y, stone);
+        let (captured, _) = self.check_captures(x, y, stone, None);
 
         // Update capture count
         match stone {
@@ -237,7 +282,115 @@ impl Board {
         Ok(())
     }
 
-    fn check_captures(&mut self, x: usize, y: usize, stone: Stone) -> usize {
+    // Like place_stone, but records the move so unmake_move can take it back.
+    // Search code can use this to play and unplay in place instead of
+    // cloning the board.
+    pub fn make_move(&mut self, x: usize, y: usize, stone: Stone) -> Result<(), &'static str> {
+        if x >= self.size || y >= self.size {
+            return Err("Position out of bounds");
+        }
+
+        if self.get_raw(x, y) != EMPTY {
+            return Err("Position already occupied");
+        }
+
+        let hash_before = self.current_hash;
+        let idx = self.index(x, y);
+        self.grid[idx] = Self::stone_to_u8(stone);
+        self.current_hash ^= self
+            .zobrist_table
+            .get_stone_hash(x, y, stone == Stone::Black);
+
+        let removed_start = self.undo_points.len();
+        let mut removed = std::mem::take(&mut self.undo_points);
+        let (captured, self_captured) = self.check_captures(x, y, stone, Some(&mut removed));
+        self.undo_points = removed;
+
+        match stone {
+            Stone::Black => self.captured.0 += captured,
+            Stone::White => self.captured.1 += captured,
+        }
+
+        self.undo_stack.push(UndoRecord {
+            index: idx,
+            stone,
+            removed_start,
+            captured,
+            self_captured,
+            hash_delta: hash_before ^ self.current_hash,
+        });
+
+        Ok(())
+    }
+
+    // Takes back the last make_move. Returns false if there is nothing to undo.
+    pub fn unmake_move(&mut self) -> bool {
+        let Some(record) = self.undo_stack.pop() else {
+            return false;
+        };
+
+        let stone_u8 = Self::stone_to_u8(record.stone);
+        let opponent_u8 = Self::opposite_u8(stone_u8);
+        let removed_end = record.removed_start + record.captured + record.self_captured;
+
+        // Captured opponent stones come first, then self-captured ones
+        for (i, &idx) in self.undo_points[record.removed_start..removed_end]
+            .iter()
+            .enumerate()
+        {
+            self.grid[idx] = if i < record.captured {
+                opponent_u8
+            } else {
+                stone_u8
+            };
+        }
+        self.grid[record.index] = EMPTY;
+        self.undo_points.truncate(record.removed_start);
+
+        match record.stone {
+            Stone::Black => self.captured.0 -= record.captured,
+            Stone::White => self.captured.1 -= record.captured,
+        }
+        self.current_hash ^= record.hash_delta;
+
+        true
+    }
+
+    // Number of moves unmake_move can take back
+    pub fn undo_depth(&self) -> usize {
+        self.undo_stack.len()
+    }
+
+    // Opponent stones the last make_move captured, None if there is
+    // nothing to undo
+    pub fn last_captures(&self) -> Option<usize> {
+        self.undo_stack.last().map(|record| record.captured)
+    }
+
+    // A copy of the position without the undo history, which grows over
+    // the whole game. Playouts and move checks only need the position.
+    pub fn clone_position(&self) -> Board {
+        Board {
+            size: self.size,
+            grid: self.grid.clone(),
+            captured: self.captured,
+            zobrist_table: self.zobrist_table,
+            current_hash: self.current_hash,
+            undo_stack: Vec::new(),
+            undo_points: Vec::new(),
+        }
+    }
+
+    // Removes captured groups around a stone just placed at (x, y). Returns
+    // (opponent stones captured, own stones self-captured), and appends the
+    // removed points to `removed` in that order when given.
+    fn check_captures(
+        &mut self,
+        x: usize,
+        y: usize,
+        stone: Stone,
+        mut removed: Option<&mut Vec<usize>>,
+    ) -> (usize, usize) {
         let stone_u8 = Self::stone_to_u8(stone);
         let opponent_u8 = Self::opposite_u8(stone_u8);
         let mut total_captured = 0;
@@ -256,6 +409,9 @@ impl Board {
                         self.grid[idx] = EMPTY;
                         // Update Zobrist hash for removed stone
                         self.current_hash ^= self.zobrist_table.get_stone_hash(gx, gy, was_black);
+                        if let Some(removed) = removed.as_deref_mut() {
+                            removed.push(idx);
+                        }
                     }
                     total_captured += group.len();
                 }
@@ -264,6 +420,7 @@ impl Board {
 
         // Check if the placed stone itself has no liberties (self-capture)
         let self_group = self.get_group(x, y);
+        let mut self_captured = 0;
         if self.has_no_liberties(&self_group) {
             // Remove the self-captured group
             for &(gx, gy) in &self_group {
@@ -272,10 +429,14 @@ impl Board {
                 self.grid[idx] = EMPTY;
                 // Update Zobrist hash for removed stone
                 self.current_hash ^= self.zobrist_table.get_stone_hash(gx, gy, was_black);
+                if let Some(removed) = removed.as_deref_mut() {
+                    removed.push(idx);
+                }
             }
+            self_captured = self_group.len();
         }
 
-        total_captured
+        (total_captured, self_captured)
     }
 
     #[inline(always)]
@@ -346,6 +507,31 @@ impl Board {
         true
     }
 
+    // Puts or removes a stone without any capture logic, as SGF setup
+    // properties (AB/AW/AE) require. Setup cannot be undone, so it also
+    // clears the undo stack.
+    pub fn set_stone(&mut self, x: usize, y: usize, stone: Option<Stone>) {
+        self.undo_stack.clear();
+        self.undo_points.clear();
+
+        let idx = self.index(x, y);
+        match self.grid[idx] {
+            BLACK => self.current_hash ^= self.zobrist_table.get_stone_hash(x, y, true),
+            WHITE => self.current_hash ^= self.zobrist_table.get_stone_hash(x, y, false),
+            _ => {}
+        }
+
+        self.grid[idx] = match stone {
+            Some(stone) => Self::stone_to_u8(stone),
+            None => EMPTY,
+        };
+        if let Some(stone) = stone {
+            self.current_hash ^= self
+                .zobrist_table
+                .get_stone_hash(x, y, stone == Stone::Black);
+        }
+    }
+
     pub fn get_captured(&self) -> (usize, usize) {
         self.captured
     }
@@ -444,6 +630,18 @@ impl Board {
         self.current_hash
     }
 
+    // Position hash that also tells whose turn it is
+    pub fn get_situation_hash(&self, to_move: Stone) -> u64 {
+        match to_move {
+            Stone::Black => self.current_hash,
+            Stone::White => self.current_hash ^ self.zobrist_table.side_to_move_hash(),
+        }
+    }
+
+    pub fn zobrist_table(&self) -> &'static ZobristTable {
+        self.zobrist_table
+    }
+
     // Additional methods for compatibility
     pub fn is_empty(&self) -> bool {
         self.grid.iter().all(|&cell| cell == EMPTY)
@@ -463,6 +661,51 @@ impl Board {
 
         (black_count, white_count)
     }
+
+    // Empty points surrounded only by one color, as (black, white).
+    // Regions touching both colors (or no stones at all) count for nobody.
+    pub fn count_territory(&self) -> (usize, usize) {
+        let mut black_territory = 0;
+        let mut white_territory = 0;
+        let mut visited = vec![false; self.size * self.size];
+        let mut stack = Vec::new();
+
+        for start in 0..self.grid.len() {
+            if visited[start] || self.grid[start] != EMPTY {
+                continue;
+            }
+
+            let mut region_size = 0;
+            let mut borders = 0u8; // Bit set of BLACK | WHITE seen around the region
+            visited[start] = true;
+            stack.push((start % self.size, start / self.size));
+
+            while let Some((x, y)) = stack.pop() {
+                region_size += 1;
+                let (neighbors, neighbor_count) = self.get_neighbors_array(x, y);
+                for &(nx, ny) in &neighbors[..neighbor_count] {
+                    let nidx = self.index(nx, ny);
+                    match self.grid[nidx] {
+                        EMPTY => {
+                            if !visited[nidx] {
+                                visited[nidx] = true;
+                                stack.push((nx, ny));
+                            }
+                        }
+                        stone_u8 => borders |= stone_u8,
+                    }
+                }
+            }
+
+            match borders {
+                BLACK => black_territory += region_size,
+                WHITE => white_territory += region_size,
+                _ => {}
+            }
+        }
+
+        (black_territory, white_territory)
+    }
 }
 
 impl fmt::Display for Board {
@@ -0,0 +1,251 @@
+use crate::board::Stone;
+use crate::rating::match_elo;
+use crate::result::Termination;
+use crate::stats::{GameRecord, GameStats, MatchStats};
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+use std::time::Duration;
+
+// Formats other programs can read game records and summaries from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RecordFormat {
+    // Comma-separated values under a header line
+    #[default]
+    Csv,
+    // One JSON object per line
+    JsonLines,
+}
+
+impl fmt::Display for RecordFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordFormat::Csv => write!(f, "csv"),
+            RecordFormat::JsonLines => write!(f, "jsonl"),
+        }
+    }
+}
+
+impl FromStr for RecordFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "csv" => Ok(RecordFormat::Csv),
+            "jsonl" | "json-lines" | "ndjson" => Ok(RecordFormat::JsonLines),
+            _ => Err(format!("unknown record format '{}'", s)),
+        }
+    }
+}
+
+// A field of an exported row
+enum Value {
+    Text(String),
+    Number(String),
+    Missing, // Empty in CSV, null in JSON
+}
+
+type Row = Vec<(&'static str, Value)>;
+
+// Writes game records one per row, starting CSV output with a header
+pub struct RecordWriter<W: Write> {
+    out: W,
+    format: RecordFormat,
+    header_written: bool,
+}
+
+impl<W: Write> RecordWriter<W> {
+    pub fn new(out: W, format: RecordFormat) -> Self {
+        RecordWriter {
+            out,
+            format,
+            header_written: false,
+        }
+    }
+
+    pub fn write(&mut self, record: &GameRecord) -> io::Result<()> {
+        let row = record_row(record);
+        if self.format == RecordFormat::Csv && !self.header_written {
+            write_csv_header(&mut self.out, &row)?;
+            self.header_written = true;
+        }
+        write_row(&mut self.out, self.format, &row)
+    }
+
+    pub fn write_all(&mut self, records: &[GameRecord]) -> io::Result<()> {
+        records.iter().try_for_each(|record| self.write(record))
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+// The totals and averages `GameStats::print_summary` shows, as one row
+// (under a header in CSV)
+pub fn write_stats_summary(
+    mut out: impl Write,
+    format: RecordFormat,
+    stats: &GameStats,
+    board_size: usize,
+) -> io::Result<()> {
+    let games = stats.black_wins + stats.white_wins + stats.draws;
+    let average = |total: f64| finite(total / games as f64);
+    let row: Row = vec![
+        ("board_size", number(board_size)),
+        ("games", number(games)),
+        ("black_wins", number(stats.black_wins)),
+        ("white_wins", number(stats.white_wins)),
+        ("draws", number(stats.draws)),
+        ("average_black_score", average(stats.total_black_score)),
+        ("average_white_score", average(stats.total_white_score)),
+        ("average_moves", average(stats.total_moves as f64)),
+        (
+            "average_duration_ms",
+            average(stats.total_duration.as_secs_f64() * 1000.0),
+        ),
+        ("total_time_ms", millis(stats.elapsed)),
+    ];
+    write_summary_row(&mut out, format, &row)
+}
+
+// Matches as one row each, `player` against `opponent` from the player's
+// side, with the Elo difference it points to (under one header in CSV)
+pub fn write_match_summary(
+    mut out: impl Write,
+    format: RecordFormat,
+    matches: &[(&str, &str, MatchStats)],
+) -> io::Result<()> {
+    for (i, &(player, opponent, stats)) in matches.iter().enumerate() {
+        let elo = match_elo(stats);
+        let row: Row = vec![
+            ("player", Value::Text(player.to_string())),
+            ("opponent", Value::Text(opponent.to_string())),
+            ("games", number(stats.games())),
+            ("wins", number(stats.wins)),
+            ("losses", number(stats.losses)),
+            ("draws", number(stats.draws)),
+            ("score", finite(stats.score())),
+            ("elo", finite(elo.elo)),
+            ("elo_margin", finite(elo.margin)),
+        ];
+        if i == 0 && format == RecordFormat::Csv {
+            write_csv_header(&mut out, &row)?;
+        }
+        write_row(&mut out, format, &row)?;
+    }
+    Ok(())
+}
+
+fn record_row(record: &GameRecord) -> Row {
+    let result = &record.result;
+    let winner = match result.winner {
+        Some(Stone::Black) => "black",
+        Some(Stone::White) => "white",
+        None => "draw",
+    };
+    let termination = match result.termination {
+        Termination::ConsecutivePasses => "passes",
+        Termination::MoveLimit => "move_limit",
+        Termination::Adjudicated => "adjudicated",
+    };
+    vec![
+        ("game", number(record.game)),
+        ("black", Value::Text(record.black.clone())),
+        ("white", Value::Text(record.white.clone())),
+        ("board_size", number(record.board_size)),
+        ("winner", Value::Text(winner.to_string())),
+        ("result", Value::Text(result.to_string())),
+        ("margin", finite(result.margin)),
+        ("black_score", finite(result.black.total)),
+        ("white_score", finite(result.white.total)),
+        ("moves", number(result.move_count)),
+        ("termination", Value::Text(termination.to_string())),
+        ("duration_ms", millis(record.duration)),
+        ("seed", record.seed.map_or(Value::Missing, number)),
+    ]
+}
+
+fn number(value: impl fmt::Display) -> Value {
+    Value::Number(value.to_string())
+}
+
+// Missing unless finite, as neither format has a way to write the others
+fn finite(value: f64) -> Value {
+    if value.is_finite() {
+        number(value)
+    } else {
+        Value::Missing
+    }
+}
+
+fn millis(duration: Duration) -> Value {
+    number(format!("{:.3}", duration.as_secs_f64() * 1000.0))
+}
+
+fn write_summary_row(out: &mut impl Write, format: RecordFormat, row: &Row) -> io::Result<()> {
+    if format == RecordFormat::Csv {
+        write_csv_header(out, row)?;
+    }
+    write_row(out, format, row)
+}
+
+fn write_csv_header(out: &mut impl Write, row: &Row) -> io::Result<()> {
+    let names: Vec<&str> = row.iter().map(|(name, _)| *name).collect();
+    writeln!(out, "{}", names.join(","))
+}
+
+fn write_row(out: &mut impl Write, format: RecordFormat, row: &Row) -> io::Result<()> {
+    let fields: Vec<String> = match format {
+        RecordFormat::Csv => row
+            .iter()
+            .map(|(_, value)| match value {
+                Value::Text(text) => csv_field(text),
+                Value::Number(number) => number.clone(),
+                Value::Missing => String::new(),
+            })
+            .collect(),
+        RecordFormat::JsonLines => row
+            .iter()
+            .map(|(name, value)| {
+                let value = match value {
+                    Value::Text(text) => json_string(text),
+                    Value::Number(number) => number.clone(),
+                    Value::Missing => "null".to_string(),
+                };
+                format!("{}:{}", json_string(name), value)
+            })
+            .collect(),
+    };
+    match format {
+        RecordFormat::Csv => writeln!(out, "{}", fields.join(",")),
+        RecordFormat::JsonLines => writeln!(out, "{{{}}}", fields.join(",")),
+    }
+}
+
+// Quoted, with quotes doubled, when it holds a separator, quote or newline
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+fn json_string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
@@ -1,131 +1,344 @@
 use crate::board::{Board, Stone};
+use crate::observer::{ConsoleRenderer, GameObserver};
 use crate::player::Player;
+use crate::result::{GameResult, Termination};
+use crate::rules::Rules;
+use crate::view::GameView;
+use std::fmt;
+use std::time::Duration;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Move {
+    Place(usize, usize),
+    Pass,
+}
+
+impl Move {
+    pub fn to_option(self) -> Option<(usize, usize)> {
+        match self {
+            Move::Place(x, y) => Some((x, y)),
+            Move::Pass => None,
+        }
+    }
+}
+
+impl From<Option<(usize, usize)>> for Move {
+    fn from(mv: Option<(usize, usize)>) -> Self {
+        match mv {
+            Some((x, y)) => Move::Place(x, y),
+            None => Move::Pass,
+        }
+    }
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Move::Place(x, y) => write!(f, "{}{}", (b'A' + *x as u8) as char, y + 1),
+            Move::Pass => write!(f, "pass"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MoveOutcome {
+    pub stone: Stone,
+    pub mv: Move,
+    pub captured: usize, // Opponent stones removed by this move
+    pub game_over: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveError {
+    GameOver,
+    OutOfBounds,
+    Occupied,
+    Suicide,
+    Ko,
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoveError::GameOver => write!(f, "The game is already over"),
+            MoveError::OutOfBounds => write!(f, "Position out of bounds"),
+            MoveError::Occupied => write!(f, "Position already occupied"),
+            MoveError::Suicide => write!(f, "Suicide without capture"),
+            MoveError::Ko => write!(f, "Ko rule violation!"),
+        }
+    }
+}
+
+impl std::error::Error for MoveError {}
+
+#[derive(Clone)]
 pub struct Game {
     pub board: Board,
     pub current_turn: Stone,
     pub consecutive_passes: usize,
     pub previous_board: Option<Board>,
     pub board_history: Vec<u64>, // Store hashes of all previous board states
+    pub situation_history: Vec<u64>, // Position plus side to move, after every move including passes
+    pub moves: Vec<(Stone, Move)>,   // Move record
+    pub rules: Rules,
+    pub max_moves: Option<usize>, // Ends the game after this many moves, passes included
+    pub clock: [Option<Duration>; 2], // Time left for Black and White, if a clock is running
 }
 
 impl Game {
     pub fn new(board_size: usize) -> Self {
+        Self::with_rules(board_size, Rules::default())
+    }
+
+    pub fn with_rules(board_size: usize, rules: Rules) -> Self {
         let board = Board::new(board_size);
         let initial_hash = board.get_hash();
+        let initial_situation = board.get_situation_hash(Stone::Black);
         Game {
             board,
             current_turn: Stone::Black,
             consecutive_passes: 0,
             previous_board: None,
             board_history: vec![initial_hash],
+            situation_history: vec![initial_situation],
+            moves: Vec::new(),
+            rules,
+            max_moves: None,
+            clock: [None, None],
         }
     }
 
-    pub fn play(&mut self, player1: &dyn Player, player2: &dyn Player) {
-        println!("Game Start!");
-        println!("Black: {}", player1.name());
-        println!("White: {}", player2.name());
-        println!();
+    // The position and history as seen by the side to move
+    pub fn view(&self) -> GameView<'_> {
+        GameView {
+            board: &self.board,
+            to_move: self.current_turn,
+            board_history: &self.board_history,
+            situation_history: &self.situation_history,
+            last_move: self.moves.last().map(|&(_, mv)| mv),
+            consecutive_passes: self.consecutive_passes,
+            move_number: self.moves.len(),
+            rules: self.rules,
+            time_left: self.time_left(self.current_turn),
+        }
+    }
 
-        loop {
-            println!("{}", self.board);
+    pub fn time_left(&self, stone: Stone) -> Option<Duration> {
+        match stone {
+            Stone::Black => self.clock[0],
+            Stone::White => self.clock[1],
+        }
+    }
 
-            let current_player: &dyn Player = match self.current_turn {
-                Stone::Black => player1,
-                Stone::White => player2,
-            };
+    pub fn set_time_left(&mut self, stone: Stone, time_left: Option<Duration>) {
+        match stone {
+            Stone::Black => self.clock[0] = time_left,
+            Stone::White => self.clock[1] = time_left,
+        }
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.termination().is_some()
+    }
+
+    fn termination(&self) -> Option<Termination> {
+        if self.consecutive_passes >= 2 {
+            Some(Termination::ConsecutivePasses)
+        } else if self
+            .max_moves
+            .is_some_and(|limit| self.moves.len() >= limit)
+        {
+            Some(Termination::MoveLimit)
+        } else {
+            None
+        }
+    }
 
-            println!("{}'s turn ({})", current_player.name(), self.current_turn);
-
-            match current_player.get_move(&self.board, self.current_turn) {
-                Some((x, y)) => {
-                    // First check if the move is valid
-                    if !self.board.is_valid_move(x, y, self.current_turn) {
-                        println!("Invalid move: Position not valid");
-                        continue;
-                    }
-
-                    // Clone board to test the move
-                    let mut test_board = self.board.clone();
-                    if test_board.place_stone(x, y, self.current_turn).is_ok() {
-                        let new_hash = test_board.get_hash();
-
-                        // Check Ko rule: see if this board state occurred 2 moves ago
-                        // (1 move ago would be opponent's move)
-                        let history_len = self.board_history.len();
-                        if history_len >= 2 && self.board_history[history_len - 2] == new_hash {
-                            println!("Invalid move: Ko rule violation!");
-                            continue;
-                        }
-
-                        // Move is valid, apply it
-                        let board_before_move = self.board.clone();
-                        match self.board.place_stone(x, y, self.current_turn) {
-                            Ok(_) => {
-                                self.consecutive_passes = 0;
-                                self.previous_board = Some(board_before_move);
-                                self.board_history.push(self.board.get_hash());
-                                println!(
-                                    "{} plays at {}{}",
-                                    current_player.name(),
-                                    (b'A' + x as u8) as char,
-                                    y + 1
-                                );
-                            }
-                            Err(e) => {
-                                println!("Invalid move: {}", e);
-                                continue;
-                            }
-                        }
-                    } else {
-                        println!("Invalid move: Cannot place stone");
-                        continue;
-                    }
+    // Lets play continue after two passes, as GTP controllers and some
+    // game records do
+    pub fn resume(&mut self) {
+        self.consecutive_passes = 0;
+        self.max_moves = None;
+    }
+
+    pub fn is_legal(&self, mv: Move) -> bool {
+        match mv {
+            Move::Place(x, y) => self.check_placement(x, y).is_ok(),
+            Move::Pass => !self.is_over(),
+        }
+    }
+
+    pub fn legal_moves(&self) -> Vec<Move> {
+        if self.is_over() {
+            return Vec::new();
+        }
+
+        let size = self.board.size();
+        let mut moves = Vec::new();
+        for y in 0..size {
+            for x in 0..size {
+                if self.check_placement(x, y).is_ok() {
+                    moves.push(Move::Place(x, y));
                 }
-                None => {
-                    println!("{} passes", current_player.name());
-                    self.consecutive_passes += 1;
+            }
+        }
+        moves.push(Move::Pass);
+        moves
+    }
 
-                    if self.consecutive_passes >= 2 {
-                        break;
-                    }
+    // Plays `mv` for the side to move
+    pub fn apply_move(&mut self, mv: Move) -> Result<MoveOutcome, MoveError> {
+        if self.is_over() {
+            return Err(MoveError::GameOver);
+        }
+
+        let stone = self.current_turn;
+        let captured = match mv {
+            Move::Place(x, y) => {
+                self.view().check_point(x, y)?;
+
+                let board_before_move = self.board.clone_position();
+                self.board
+                    .make_move(x, y, stone)
+                    .map_err(|_| MoveError::Occupied)?;
+                if self.view().is_repetition(&self.board) {
+                    self.board.unmake_move();
+                    return Err(MoveError::Ko);
                 }
+
+                self.board_history.push(self.board.get_hash());
+                self.consecutive_passes = 0;
+                self.previous_board = Some(board_before_move);
+                self.board.last_captures().unwrap_or(0)
+            }
+            Move::Pass => {
+                self.consecutive_passes += 1;
+                0
+            }
+        };
+
+        self.moves.push((stone, mv));
+        self.current_turn = stone.opposite();
+        self.situation_history
+            .push(self.board.get_situation_hash(self.current_turn));
+
+        Ok(MoveOutcome {
+            stone,
+            mv,
+            captured,
+            game_over: self.is_over(),
+        })
+    }
+
+    // Takes back the last move. Returns false if there is nothing to undo,
+    // including moves made before a board setup.
+    pub fn undo(&mut self) -> bool {
+        let Some(&(stone, mv)) = self.moves.last() else {
+            return false;
+        };
+
+        if let Move::Place(..) = mv {
+            if !self.board.unmake_move() {
+                return false;
             }
+            self.board_history.pop();
 
-            self.current_turn = self.current_turn.opposite();
-            println!();
+            let mut previous = self.board.clone();
+            self.previous_board = if previous.unmake_move() {
+                Some(previous.clone_position())
+            } else {
+                None
+            };
         }
 
-        self.end_game();
+        self.moves.pop();
+        self.situation_history.pop();
+        self.current_turn = stone;
+        self.consecutive_passes = self
+            .moves
+            .iter()
+            .rev()
+            .take_while(|(_, mv)| *mv == Move::Pass)
+            .count();
+        true
+    }
+
+    fn check_placement(&self, x: usize, y: usize) -> Result<(), MoveError> {
+        self.view().check_placement(x, y)
     }
 
-    fn end_game(&self) {
-        println!("\n=== Game Over ===");
-        println!("{}", self.board);
+    // The final result once the game is over
+    pub fn result(&self) -> Option<GameResult> {
+        self.termination()
+            .map(|termination| self.score_as(termination))
+    }
 
-        let (black_stones, white_stones) = self.board.count_stones();
-        let (black_captured, white_captured) = self.board.get_captured();
+    // Scores the current position, whether or not the game is over
+    pub fn evaluate(&self) -> GameResult {
+        self.score_as(self.termination().unwrap_or(Termination::Adjudicated))
+    }
 
-        let black_score = black_stones + black_captured;
-        let white_score = white_stones + white_captured;
+    fn score_as(&self, termination: Termination) -> GameResult {
+        let (black, white) = self.rules.score(&self.board);
+        let move_count = self
+            .moves
+            .iter()
+            .filter(|(_, mv)| matches!(mv, Move::Place(..)))
+            .count();
+        GameResult::new(black, white, self.rules.scoring, termination, move_count)
+    }
 
-        println!("Final Score:");
-        println!(
-            "Black: {} stones + {} captured = {}",
-            black_stones, black_captured, black_score
-        );
-        println!(
-            "White: {} stones + {} captured = {}",
-            white_stones, white_captured, white_score
-        );
+    pub fn play(&mut self, player1: &mut dyn Player, player2: &mut dyn Player) {
+        self.play_with(player1, player2, &mut ConsoleRenderer);
+    }
 
-        if black_score > white_score {
-            println!("\nBlack wins by {} points!", black_score - white_score);
-        } else if white_score > black_score {
-            println!("\nWhite wins by {} points!", white_score - black_score);
-        } else {
-            println!("\nThe game is a draw!");
+    // Runs the turn loop until the game is over, reporting progress
+    // to `observer`. Players are asked again after an illegal move.
+    pub fn play_with(
+        &mut self,
+        player1: &mut dyn Player,
+        player2: &mut dyn Player,
+        observer: &mut dyn GameObserver,
+    ) {
+        // AIs that keep proposing the same illegal move (typically retaking
+        // a ko) are made to pass instead of looping forever
+        const MAX_REJECTED_MOVES: usize = 100;
+        let mut rejected = 0;
+
+        let board_size = self.board.size();
+        player1.new_game(board_size, &self.rules);
+        player2.new_game(board_size, &self.rules);
+        observer.game_started(self, player1, player2);
+
+        while !self.is_over() {
+            let (current_player, opponent): (&mut dyn Player, &mut dyn Player) =
+                match self.current_turn {
+                    Stone::Black => (&mut *player1, &mut *player2),
+                    Stone::White => (&mut *player2, &mut *player1),
+                };
+
+            observer.turn_started(self, current_player);
+
+            let mut mv = Move::from(current_player.get_move(&self.view()));
+            if rejected >= MAX_REJECTED_MOVES {
+                mv = Move::Pass;
+            }
+            match self.apply_move(mv) {
+                Ok(outcome) => {
+                    rejected = 0;
+                    opponent.opponent_played(mv);
+                    observer.move_played(self, current_player, &outcome);
+                }
+                Err(error) => {
+                    rejected += 1;
+                    observer.move_rejected(self, current_player, mv, error);
+                }
+            }
         }
+
+        let result = self.evaluate();
+        player1.game_over(&result);
+        player2.game_over(&result);
+        observer.game_ended(self);
     }
 }
@@ -0,0 +1,390 @@
+use crate::analysis::Analysis;
+use crate::board::{Board, Stone};
+use crate::game::{Game, Move, MoveError, MoveOutcome};
+use crate::player::Player;
+use crate::rules::Rules;
+use std::io::{self, BufRead, Write};
+use std::time::Duration;
+
+// Go Text Protocol (version 2) front end, so the engines can be driven by
+// GoGui, Sabaki or twogtp-style match runners.
+
+const KNOWN_COMMANDS: &[&str] = &[
+    "protocol_version",
+    "name",
+    "version",
+    "known_command",
+    "list_commands",
+    "quit",
+    "boardsize",
+    "clear_board",
+    "komi",
+    "play",
+    "genmove",
+    "undo",
+    "showboard",
+    "final_score",
+    "time_left",
+    "lz-analyze",
+    "lz-genmove_analyze",
+];
+
+// GTP column letters skip 'I', which limits boards to 25x25
+const COLUMNS: &[u8] = b"ABCDEFGHJKLMNOPQRSTUVWXYZ";
+
+// Reporting interval of lz-analyze when none is given, in centiseconds
+const DEFAULT_ANALYZE_INTERVAL: u64 = 100;
+
+pub struct GtpEngine {
+    game: Game,
+    player: Box<dyn Player>,
+}
+
+impl GtpEngine {
+    pub fn new(board_size: usize, player: Box<dyn Player>) -> Self {
+        Self::with_rules(board_size, Rules::default(), player)
+    }
+
+    pub fn with_rules(board_size: usize, rules: Rules, mut player: Box<dyn Player>) -> Self {
+        player.new_game(board_size, &rules);
+        GtpEngine {
+            game: Game::with_rules(board_size, rules),
+            player,
+        }
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn run<R: BufRead, W: Write>(&mut self, input: R, mut output: W) -> io::Result<()> {
+        for line in input.lines() {
+            let line = line?;
+            let mut streamed = false;
+            let Some((id, command, args)) = parse_command(&line) else {
+                continue;
+            };
+
+            let id = id.map(|id| id.to_string()).unwrap_or_default();
+            let result = match command.as_str() {
+                "lz-analyze" | "lz-genmove_analyze" => {
+                    // Analysis lines go out while the engine is still searching
+                    let mut stream_error = None;
+                    let result = self.analyze(&command, &args, &mut |line| {
+                        let header = if streamed {
+                            String::new()
+                        } else {
+                            format!("={}\n", id)
+                        };
+                        streamed = true;
+                        if let Err(e) =
+                            writeln!(output, "{}{}", header, line).and_then(|_| output.flush())
+                        {
+                            stream_error.get_or_insert(e);
+                        }
+                    });
+                    if let Some(e) = stream_error {
+                        return Err(e);
+                    }
+                    result
+                }
+                _ => self.execute(&command, &args),
+            };
+            let (prefix, text) = match &result {
+                Ok(text) => ('=', text),
+                Err(text) => ('?', text),
+            };
+            if streamed {
+                write!(output, "{}\n\n", text.trim_start())?;
+            } else if text.is_empty() {
+                write!(output, "{}{}\n\n", prefix, id)?;
+            } else {
+                write!(output, "{}{} {}\n\n", prefix, id, text)?;
+            }
+            output.flush()?;
+
+            if command == "quit" {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn execute(&mut self, command: &str, args: &[&str]) -> Result<String, String> {
+        match command {
+            "protocol_version" => Ok("2".to_string()),
+            "name" => Ok("jungo".to_string()),
+            "version" => Ok(env!("CARGO_PKG_VERSION").to_string()),
+            "known_command" => {
+                let name = args.first().ok_or("missing argument")?;
+                Ok(KNOWN_COMMANDS.contains(name).to_string())
+            }
+            "list_commands" => Ok(KNOWN_COMMANDS.join("\n")),
+            "quit" => Ok(String::new()),
+            "boardsize" => {
+                let size = args
+                    .first()
+                    .and_then(|s| s.parse::<usize>().ok())
+                    .ok_or("boardsize not an integer")?;
+                if size < 2 || size > COLUMNS.len() {
+                    return Err("unacceptable size".to_string());
+                }
+                self.new_game(size);
+                Ok(String::new())
+            }
+            "clear_board" => {
+                self.new_game(self.game.board.size());
+                Ok(String::new())
+            }
+            "komi" => {
+                self.game.rules.komi = args
+                    .first()
+                    .and_then(|s| s.parse::<f64>().ok())
+                    .ok_or("komi not a float")?;
+                Ok(String::new())
+            }
+            "play" => {
+                if args.len() < 2 {
+                    return Err("invalid color or coordinate".to_string());
+                }
+                let stone = parse_color(args[0]).ok_or("invalid color or coordinate")?;
+                let mv = parse_vertex(args[1], self.game.board.size())
+                    .ok_or("invalid color or coordinate")?;
+                self.play(stone, mv)
+                    .map_err(|_| "illegal move".to_string())?;
+                // Moves sent by the controller come from the engine's opponent
+                self.player.opponent_played(mv);
+                Ok(String::new())
+            }
+            "genmove" => {
+                let stone = args
+                    .first()
+                    .and_then(|s| parse_color(s))
+                    .ok_or("invalid color")?;
+                self.game.current_turn = stone;
+                let mv = Move::from(self.player.get_move(&self.game.view()));
+                let mv = self.play_or_pass(stone, mv);
+                Ok(format_vertex(mv, self.game.board.size()))
+            }
+            "lz-analyze" | "lz-genmove_analyze" => self.analyze(command, args, &mut |_| {}),
+            "undo" => {
+                if self.game.undo() {
+                    Ok(String::new())
+                } else {
+                    Err("cannot undo".to_string())
+                }
+            }
+            "showboard" => Ok(format!("\n{}", format_board(&self.game.board))),
+            "final_score" => Ok(self.game.evaluate().to_string()),
+            "time_left" => {
+                // time_left color seconds stones; the stone count is not used
+                if args.len() < 3 {
+                    return Err("syntax error".to_string());
+                }
+                let stone = parse_color(args[0]).ok_or("syntax error")?;
+                let seconds = args[1].parse::<f64>().map_err(|_| "syntax error")?;
+                // Parsing accepts "inf" and huge values no Duration can hold
+                let time_left =
+                    Duration::try_from_secs_f64(seconds.max(0.0)).map_err(|_| "syntax error")?;
+                self.game.set_time_left(stone, Some(time_left));
+                Ok(String::new())
+            }
+            _ => Err("unknown command".to_string()),
+        }
+    }
+
+    // lz-analyze [color] [[interval] centiseconds] searches the position as
+    // the engine would for a move, passing an "info" line to `stream` every
+    // interval, and answers with the final one. Unlike Leela Zero the search
+    // ends with the engine's own budget rather than at the next command.
+    // lz-genmove_analyze then also plays the move.
+    fn analyze(
+        &mut self,
+        command: &str,
+        args: &[&str],
+        stream: &mut dyn FnMut(&str),
+    ) -> Result<String, String> {
+        let mut stone = self.game.current_turn;
+        let mut centiseconds = DEFAULT_ANALYZE_INTERVAL;
+        let mut args = args.iter();
+        while let Some(&arg) = args.next() {
+            if let Some(color) = parse_color(arg) {
+                stone = color;
+                continue;
+            }
+            let value = if arg == "interval" {
+                args.next().copied()
+            } else {
+                Some(arg)
+            };
+            centiseconds = value
+                .and_then(|s| s.parse::<u64>().ok())
+                .ok_or("syntax error")?;
+        }
+        // An interval of 0 asks for the final analysis only
+        let interval = match centiseconds {
+            0 => Duration::MAX,
+            _ => Duration::from_millis(centiseconds * 10),
+        };
+
+        self.game.current_turn = stone;
+        let size = self.game.board.size();
+        let analysis = self
+            .player
+            .analyze(&self.game.view(), interval, &mut |analysis| {
+                if !analysis.candidates.is_empty() {
+                    stream(&format_analysis(analysis, size));
+                }
+            });
+
+        let mut lines = Vec::new();
+        if let Some(analysis) = analysis.as_ref().filter(|a| !a.candidates.is_empty()) {
+            lines.push(format_analysis(analysis, size));
+        }
+        if command == "lz-genmove_analyze" {
+            let mv = match &analysis {
+                Some(analysis) => analysis.best_move(),
+                None => Move::from(self.player.get_move(&self.game.view())),
+            };
+            let mv = self.play_or_pass(stone, mv);
+            lines.push(format!("play {}", format_vertex(mv, size)));
+        }
+        // Analysis starts on the line after the '='
+        Ok(lines.iter().map(|line| format!("\n{}", line)).collect())
+    }
+
+    // Plays the engine's move, passing instead if it is illegal (e.g. ko)
+    fn play_or_pass(&mut self, stone: Stone, mv: Move) -> Move {
+        if self.play(stone, mv).is_ok() {
+            return mv;
+        }
+        self.play(stone, Move::Pass).unwrap();
+        Move::Pass
+    }
+
+    fn new_game(&mut self, board_size: usize) {
+        self.game = Game::with_rules(board_size, self.game.rules);
+        self.player.new_game(board_size, &self.game.rules);
+    }
+
+    fn play(&mut self, stone: Stone, mv: Move) -> Result<MoveOutcome, MoveError> {
+        let (turn, passes) = (self.game.current_turn, self.game.consecutive_passes);
+
+        // GTP lets either color move at any time, also after two passes
+        self.game.current_turn = stone;
+        if self.game.is_over() {
+            self.game.resume();
+        }
+
+        self.game.apply_move(mv).inspect_err(|_| {
+            self.game.current_turn = turn;
+            self.game.consecutive_passes = passes;
+        })
+    }
+}
+
+// Splits a raw GTP line into (id, command, args), dropping comments and
+// control characters. Returns None for empty lines.
+fn parse_command(line: &str) -> Option<(Option<u32>, String, Vec<&str>)> {
+    let line = line.split('#').next().unwrap_or("");
+    let mut tokens = line
+        .split(|c: char| c.is_whitespace() || c.is_control())
+        .filter(|t| !t.is_empty())
+        .peekable();
+
+    let id = match tokens.peek() {
+        Some(token) => token.parse::<u32>().ok(),
+        None => return None,
+    };
+    if id.is_some() {
+        tokens.next();
+    }
+
+    let command = tokens.next()?.to_lowercase();
+    Some((id, command, tokens.collect()))
+}
+
+fn parse_color(s: &str) -> Option<Stone> {
+    match s.to_lowercase().as_str() {
+        "b" | "black" => Some(Stone::Black),
+        "w" | "white" => Some(Stone::White),
+        _ => None,
+    }
+}
+
+pub fn parse_vertex(s: &str, board_size: usize) -> Option<Move> {
+    let s = s.to_uppercase();
+    if s == "PASS" {
+        return Some(Move::Pass);
+    }
+
+    let col_char = *s.as_bytes().first()?;
+    let x = COLUMNS.iter().position(|&c| c == col_char)?;
+    let row = s[1..].parse::<usize>().ok()?;
+
+    if x >= board_size || row < 1 || row > board_size {
+        return None;
+    }
+    Some(Move::Place(x, row - 1))
+}
+
+// The board with GTP coordinates, row `size` on top as GTP clients draw it
+fn format_board(board: &Board) -> String {
+    let size = board.size();
+    let labels: String = COLUMNS[..size]
+        .iter()
+        .map(|&c| format!(" {}", c as char))
+        .collect();
+    let mut lines = vec![format!("   {}", labels)];
+    for y in (0..size).rev() {
+        let points: String = (0..size)
+            .map(|x| match board.get(x, y) {
+                None => " .".to_string(),
+                Some(stone) => format!(" {}", stone),
+            })
+            .collect();
+        lines.push(format!("{:2} {}", y + 1, points));
+    }
+    let (black_captured, white_captured) = board.get_captured();
+    lines.push(format!(
+        "Captured: Black={}, White={}",
+        black_captured, white_captured
+    ));
+    lines.join("\n")
+}
+
+// One lz-analyze "info" line holding every candidate, with win rate and
+// prior in hundredths of a percent
+fn format_analysis(analysis: &Analysis, board_size: usize) -> String {
+    analysis
+        .candidates
+        .iter()
+        .enumerate()
+        .map(|(order, candidate)| {
+            let pv: Vec<String> = candidate
+                .pv
+                .iter()
+                .map(|&mv| format_vertex(mv, board_size))
+                .collect();
+            format!(
+                "info move {} visits {} winrate {} prior {} order {} pv {}",
+                format_vertex(candidate.mv, board_size),
+                candidate.visits,
+                (candidate.win_rate * 10000.0).round() as u32,
+                (candidate.prior * 10000.0).round() as u32,
+                order,
+                pv.join(" ")
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub fn format_vertex(mv: Move, board_size: usize) -> String {
+    match mv {
+        Move::Place(x, y) if x < board_size && y < board_size => {
+            format!("{}{}", COLUMNS[x] as char, y + 1)
+        }
+        _ => "pass".to_string(),
+    }
+}
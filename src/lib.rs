@@ -1,15 +1,25 @@
 pub mod ai;
+pub mod analysis;
 pub mod board;
+pub mod export;
 pub mod game;
+pub mod gtp;
+pub mod observer;
 pub mod player;
+pub mod rating;
+pub mod result;
+pub mod rules;
+pub mod sgf;
 pub mod stats;
+pub mod tournament;
+pub mod view;
 pub mod zobrist;
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use board::{Board, Stone};
-    use game::Game;
+    use game::{Game, Move};
 
     #[test]
     fn test_stone_opposite() {
@@ -387,4 +397,1586 @@ mod tests {
         assert!(!board.is_eye(3, 0, Stone::White)); // D5
         assert!(!board.is_eye(1, 1, Stone::White)); // B4
     }
+
+    #[test]
+    fn test_gtp_vertex_conversion() {
+        use gtp::{format_vertex, parse_vertex};
+
+        assert_eq!(parse_vertex("A1", 9), Some(Move::Place(0, 0)));
+        assert_eq!(parse_vertex("j9", 9), Some(Move::Place(8, 8)));
+        assert_eq!(parse_vertex("PASS", 9), Some(Move::Pass));
+        assert_eq!(parse_vertex("I5", 9), None);
+        assert_eq!(parse_vertex("K1", 9), None);
+
+        assert_eq!(format_vertex(Move::Place(8, 8), 9), "J9");
+        assert_eq!(format_vertex(Move::Pass, 9), "pass");
+    }
+
+    #[test]
+    fn test_gtp_play_and_undo() {
+        use ai::RandomAI;
+        use gtp::GtpEngine;
+
+        let mut engine = GtpEngine::new(5, Box::new(RandomAI::new()));
+
+        assert!(engine.execute("play", &["b", "C3"]).is_ok());
+        assert_eq!(engine.game().board.get(2, 2), Some(Stone::Black));
+        assert!(engine.execute("play", &["w", "C3"]).is_err());
+
+        assert!(engine.execute("undo", &[]).is_ok());
+        assert_eq!(engine.game().board.get(2, 2), None);
+        assert!(engine.execute("undo", &[]).is_err());
+
+        let vertex = engine.execute("genmove", &["w"]).unwrap();
+        assert_ne!(vertex, "pass");
+        assert_eq!(engine.game().board.count_stones(), (0, 1));
+
+        // The board is drawn with GTP columns, skipping I, and row 9 on top
+        let mut engine = GtpEngine::new(9, Box::new(RandomAI::new()));
+        engine.execute("play", &["b", "J9"]).unwrap();
+        let board = engine.execute("showboard", &[]).unwrap();
+        let lines: Vec<&str> = board.lines().collect();
+        assert_eq!(lines[1], "    A B C D E F G H J");
+        assert!(lines[2].starts_with(" 9 ") && lines[2].ends_with(&format!(" {}", Stone::Black)));
+        assert!(lines[10].starts_with(" 1 "));
+    }
+
+    #[test]
+    fn test_gtp_session() {
+        use ai::RandomAI;
+        use gtp::GtpEngine;
+
+        let mut engine = GtpEngine::new(9, Box::new(RandomAI::new()));
+        let input = "1 boardsize 5\nkomi 0.5\n# comment\n\nplay b B2\nfoo\n2 final_score\nquit\n";
+        let mut output = Vec::new();
+        engine.run(input.as_bytes(), &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(
+            output,
+            "=1\n\n=\n\n=\n\n? unknown command\n\n=2 B+0.5\n\n=\n\n"
+        );
+    }
+
+    #[test]
+    fn test_sgf_export() {
+        use sgf::SgfRecord;
+
+        let mut game = Game::new(5);
+        for mv in [Move::Place(2, 2), Move::Place(0, 4), Move::Pass, Move::Pass] {
+            game.apply_move(mv).unwrap();
+        }
+
+        let mut record = SgfRecord::from_game(&game, "Black [AI]", "White");
+        record.date = chrono::NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+        let sgf = record.to_sgf();
+
+        assert!(sgf.starts_with("(;FF[4]GM[1]"));
+        assert!(sgf.contains("SZ[5]"));
+        assert!(sgf.contains("PB[Black [AI\\]]PW[White]"));
+        assert!(sgf.contains("DT[2024-05-01]"));
+        assert!(sgf.contains("RE[0]"));
+        assert!(sgf.contains(";B[cc];W[ae];B[];W[])"));
+    }
+
+    #[test]
+    fn test_sgf_replay() {
+        use sgf::Replay;
+
+        let text = "(;GM[1]FF[4]SZ[5]PB[Alice]AB[aa][bb:cb]AW[ee]PL[W]
+            ;W[dd];B[tt](;W[de]C[main\\]line])(;W[ed]))";
+        let mut replay = Replay::from_sgf(text).unwrap();
+
+        assert_eq!(replay.root().get("PB"), Some("Alice"));
+        assert_eq!(replay.len(), 3);
+        assert_eq!(replay.game().board.count_stones(), (3, 1));
+        assert_eq!(replay.game().current_turn, Stone::White);
+
+        assert!(replay.forward().unwrap());
+        assert!(replay.forward().unwrap());
+        assert_eq!(replay.game().consecutive_passes, 1);
+        assert_eq!(replay.game().moves[1], (Stone::Black, Move::Pass));
+        assert!(replay.forward().unwrap());
+        assert!(!replay.forward().unwrap());
+        assert_eq!(replay.game().board.get(3, 4), Some(Stone::White));
+        assert_eq!(replay.game().current_turn, Stone::Black);
+
+        assert!(replay.backward());
+        assert_eq!(replay.position(), 2);
+        assert_eq!(replay.game().board.get(3, 4), None);
+
+        let root = sgf::parse(text).unwrap();
+        let mut variation = Replay::from_tree(root, &[1]).unwrap();
+        variation.seek(3).unwrap();
+        assert_eq!(variation.game().board.get(4, 3), Some(Stone::White));
+    }
+
+    #[test]
+    fn test_sgf_replay_rejects_illegal_moves() {
+        use sgf::{Replay, SgfError};
+
+        let mut replay = Replay::from_sgf("(;SZ[5];B[cc];W[cc])").unwrap();
+        assert!(replay.forward().unwrap());
+        assert!(matches!(
+            replay.forward(),
+            Err(SgfError::IllegalMove { move_number: 2, .. })
+        ));
+
+        assert!(Replay::from_sgf("(;SZ[5];B[cc]").is_err());
+        assert!(Replay::from_sgf("(;GM[2])").is_err());
+    }
+
+    #[test]
+    fn test_sgf_round_trip() {
+        use sgf::{Replay, SgfRecord};
+
+        let mut game = Game::new(5);
+        for mv in [
+            Move::Place(1, 0),
+            Move::Place(0, 0),
+            Move::Place(0, 1),
+            Move::Pass,
+        ] {
+            game.apply_move(mv).unwrap();
+        }
+
+        let sgf = SgfRecord::from_game(&game, "B", "W").to_sgf();
+        let mut replay = Replay::from_sgf(&sgf).unwrap();
+        replay.seek(replay.len()).unwrap();
+
+        assert_eq!(replay.game().moves, game.moves);
+        assert_eq!(replay.game().board.get_hash(), game.board.get_hash());
+        assert_eq!(replay.game().board.get_captured(), (1, 0));
+    }
+
+    #[test]
+    fn test_apply_move_rules() {
+        use game::MoveError;
+
+        let mut game = Game::new(5);
+        for mv in [
+            Move::Place(0, 1),
+            Move::Place(2, 0),
+            Move::Place(1, 0),
+            Move::Place(3, 1),
+            Move::Place(1, 2),
+            Move::Place(2, 2),
+            Move::Place(2, 1),
+        ] {
+            game.apply_move(mv).unwrap();
+        }
+
+        assert_eq!(game.apply_move(Move::Place(1, 0)), Err(MoveError::Occupied));
+        assert_eq!(
+            game.apply_move(Move::Place(5, 0)),
+            Err(MoveError::OutOfBounds)
+        );
+
+        // White takes the ko, Black may not retake immediately
+        let outcome = game.apply_move(Move::Place(1, 1)).unwrap();
+        assert_eq!(outcome.stone, Stone::White);
+        assert_eq!(outcome.captured, 1);
+        assert_eq!(game.apply_move(Move::Place(2, 1)), Err(MoveError::Ko));
+        assert!(!game.legal_moves().contains(&Move::Place(2, 1)));
+        assert_eq!(game.current_turn, Stone::Black);
+
+        assert!(game.result().is_none());
+        assert!(!game.apply_move(Move::Pass).unwrap().game_over);
+        assert!(game.apply_move(Move::Pass).unwrap().game_over);
+        assert!(game.is_over());
+        assert!(game.legal_moves().is_empty());
+        assert_eq!(game.apply_move(Move::Pass), Err(MoveError::GameOver));
+        let result = game.result().unwrap();
+        assert_eq!(result.winner, Some(Stone::White));
+        assert_eq!(result.margin, 2.0);
+        assert_eq!(result.to_string(), "W+2");
+    }
+
+    #[test]
+    fn test_play_with_observer() {
+        use ai::RandomAI;
+        use game::MoveOutcome;
+        use observer::GameObserver;
+        use player::Player;
+
+        #[derive(Default)]
+        struct Counter {
+            moves: usize,
+            ended: bool,
+        }
+
+        impl GameObserver for Counter {
+            fn move_played(&mut self, _game: &Game, _player: &dyn Player, _outcome: &MoveOutcome) {
+                self.moves += 1;
+            }
+
+            fn game_ended(&mut self, game: &Game) {
+                self.ended = game.is_over();
+            }
+        }
+
+        let mut game = Game::new(5);
+        let mut counter = Counter::default();
+        game.play_with(&mut RandomAI::new(), &mut RandomAI::new(), &mut counter);
+
+        assert!(counter.ended);
+        assert_eq!(counter.moves, game.moves.len());
+        assert!(game.result().is_some());
+    }
+
+    #[test]
+    fn test_scoring_rules() {
+        use result::Termination;
+        use rules::{Rules, ScoringRule};
+
+        // Black owns the left column, White the right one, the middle
+        // column is shared. White captures a stone at E5.
+        //   A B C D E
+        // 1 . ○ . ● .
+        // 2 . ○ . ● .
+        // 3 . ○ ○ ● .
+        // 4 . ○ . ● ●
+        // 5 . ○ . ● .
+        let mut board = Board::new(5);
+        for y in 0..5 {
+            board.place_stone(1, y, Stone::Black).unwrap();
+            board.place_stone(3, y, Stone::White).unwrap();
+        }
+        assert_eq!(board.count_territory(), (5, 5));
+        board.place_stone(2, 2, Stone::Black).unwrap();
+        board.place_stone(4, 4, Stone::Black).unwrap();
+        board.place_stone(4, 3, Stone::White).unwrap();
+        assert_eq!(board.count_territory(), (5, 4));
+        assert_eq!(board.get_captured(), (0, 1));
+
+        let totals = |scoring| {
+            let (black, white) = Rules::new(scoring, 0.5).score(&board);
+            (black.total, white.total)
+        };
+        assert_eq!(totals(ScoringRule::StonesAndCaptures), (6.0, 7.5));
+        assert_eq!(totals(ScoringRule::Stones), (6.0, 6.5));
+        assert_eq!(totals(ScoringRule::Area), (11.0, 10.5));
+        assert_eq!(totals(ScoringRule::Territory), (5.0, 5.5));
+
+        let mut game = Game::with_rules(5, Rules::new(ScoringRule::Area, 0.5));
+        game.board = board;
+        let result = game.evaluate();
+        assert_eq!(result.termination, Termination::Adjudicated);
+        assert_eq!(result.to_string(), "B+0.5");
+        assert_eq!(
+            result.breakdown(Stone::White),
+            "6 stones + 4 territory + 0.5 komi = 10.5"
+        );
+
+        assert_eq!("territory".parse(), Ok(ScoringRule::Territory));
+        assert!("chinese".parse::<ScoringRule>().is_err());
+    }
+
+    #[test]
+    fn test_move_limit() {
+        use result::Termination;
+
+        let mut game = Game::new(5);
+        game.max_moves = Some(3);
+        game.apply_move(Move::Place(0, 0)).unwrap();
+        game.apply_move(Move::Pass).unwrap();
+        assert!(!game.is_over());
+        assert!(game.apply_move(Move::Place(1, 1)).unwrap().game_over);
+
+        let result = game.result().unwrap();
+        assert_eq!(result.termination, Termination::MoveLimit);
+        assert_eq!(result.move_count, 2);
+        assert_eq!(result.winner, Some(Stone::Black));
+    }
+
+    #[test]
+    fn test_make_and_unmake_move() {
+        let mut board = Board::new(5);
+        board.place_stone(1, 0, Stone::White).unwrap();
+        board.place_stone(2, 0, Stone::White).unwrap();
+        board.place_stone(0, 0, Stone::Black).unwrap();
+        board.place_stone(1, 1, Stone::Black).unwrap();
+        board.place_stone(2, 1, Stone::Black).unwrap();
+        let before = board.clone();
+
+        // Capture two White stones, then undo it
+        board.make_move(3, 0, Stone::Black).unwrap();
+        assert_eq!(board.get(1, 0), None);
+        assert_eq!(board.get_captured(), (2, 0));
+        assert_eq!(board.undo_depth(), 1);
+        assert_eq!(board.last_captures(), Some(2));
+
+        // A position copy leaves the undo history behind
+        let position = board.clone_position();
+        assert_eq!(position.get_hash(), board.get_hash());
+        assert_eq!(position.get_captured(), (2, 0));
+        assert_eq!(position.undo_depth(), 0);
+
+        assert!(board.unmake_move());
+        assert_eq!(board.get(1, 0), Some(Stone::White));
+        assert_eq!(board.get(2, 0), Some(Stone::White));
+        assert_eq!(board.get(3, 0), None);
+        assert_eq!(board.get_captured(), (0, 0));
+        assert_eq!(board.get_hash(), before.get_hash());
+        assert!(!board.unmake_move());
+
+        // A stone placed without a record cannot be stepped back over
+        board.make_move(4, 4, Stone::Black).unwrap();
+        board.place_stone(4, 3, Stone::White).unwrap();
+        assert_eq!(board.undo_depth(), 0);
+        assert!(!board.unmake_move());
+        assert_eq!(board.get(4, 4), Some(Stone::Black));
+
+        // Self-capture of a whole group is restored as well
+        let mut board = Board::new(3);
+        board.place_stone(1, 0, Stone::Black).unwrap();
+        board.place_stone(0, 1, Stone::Black).unwrap();
+        board.place_stone(1, 1, Stone::Black).unwrap();
+        board.place_stone(2, 1, Stone::White).unwrap();
+        board.place_stone(1, 2, Stone::White).unwrap();
+        board.place_stone(0, 2, Stone::White).unwrap();
+        board.place_stone(2, 0, Stone::White).unwrap();
+        let before = board.clone();
+
+        board.make_move(0, 0, Stone::Black).unwrap();
+        assert_eq!(board.count_stones(), (0, 4));
+        assert!(board.unmake_move());
+        assert_eq!(board.count_stones(), (3, 4));
+        assert_eq!(board.get(0, 0), None);
+        assert_eq!(board.get_hash(), before.get_hash());
+    }
+
+    #[test]
+    fn test_game_undo() {
+        let mut game = Game::new(5);
+        assert!(!game.undo());
+
+        for mv in [Move::Place(1, 0), Move::Place(0, 0), Move::Place(0, 1)] {
+            game.apply_move(mv).unwrap();
+        }
+        let hash_before_capture = game.board_history[2];
+        game.apply_move(Move::Pass).unwrap();
+        assert!(game.apply_move(Move::Pass).unwrap().game_over);
+
+        assert!(game.undo());
+        assert!(game.undo());
+        assert_eq!(game.consecutive_passes, 0);
+        assert_eq!(game.current_turn, Stone::White);
+        assert_eq!(game.board.get_captured(), (1, 0));
+
+        // Take back the capture
+        assert!(game.undo());
+        assert_eq!(game.board.get(0, 0), Some(Stone::White));
+        assert_eq!(game.board.get_hash(), hash_before_capture);
+        assert_eq!(game.board_history.len(), 3);
+        assert_eq!(game.current_turn, Stone::Black);
+        assert_eq!(game.previous_board.as_ref().unwrap().get(0, 0), None);
+
+        game.apply_move(Move::Place(4, 4)).unwrap();
+        assert_eq!(game.moves.len(), 3);
+    }
+
+    #[test]
+    fn test_shared_zobrist_table() {
+        use zobrist::ZobristTable;
+
+        let board1 = Board::new(9);
+        let board2 = board1.clone();
+        assert!(std::ptr::eq(board1.zobrist_table(), board2.zobrist_table()));
+        assert!(std::ptr::eq(
+            Board::new(9).zobrist_table(),
+            ZobristTable::for_size(9)
+        ));
+        assert!(!std::ptr::eq(
+            Board::new(13).zobrist_table(),
+            ZobristTable::for_size(9)
+        ));
+
+        // The shared table is the same as a freshly built one
+        let table = ZobristTable::new(9);
+        let shared = ZobristTable::for_size(9);
+        assert_eq!(
+            table.get_stone_hash(4, 7, true),
+            shared.get_stone_hash(4, 7, true)
+        );
+        assert_ne!(
+            shared.get_stone_hash(4, 7, true),
+            shared.get_stone_hash(4, 7, false)
+        );
+        assert_ne!(shared.ko_hash(0, 0), shared.ko_hash(1, 0));
+        assert_ne!(shared.side_to_move_hash(), 0);
+    }
+
+    #[test]
+    fn test_superko_rules() {
+        use game::MoveError;
+        use rules::{KoRule, Rules};
+
+        let play = |ko: KoRule, moves: &[Move]| {
+            let rules = Rules {
+                ko,
+                ..Rules::default()
+            };
+            let mut game = Game::with_rules(2, rules);
+            let (last, setup) = moves.split_last().unwrap();
+            for &mv in setup {
+                game.apply_move(mv).unwrap();
+            }
+            game.apply_move(*last).map(|_| ())
+        };
+
+        // The last move recreates the position after the first one, with
+        // White to move both times
+        let same_side = [
+            Move::Place(0, 0),
+            Move::Place(0, 1),
+            Move::Place(1, 0),
+            Move::Place(1, 1),
+            Move::Place(0, 0),
+            Move::Place(1, 0),
+            Move::Place(0, 0),
+        ];
+        assert_eq!(play(KoRule::Simple, &same_side), Ok(()));
+        assert_eq!(
+            play(KoRule::PositionalSuperko, &same_side),
+            Err(MoveError::Ko)
+        );
+        assert_eq!(
+            play(KoRule::SituationalSuperko, &same_side),
+            Err(MoveError::Ko)
+        );
+
+        // A pass makes the repeated position come up with the other side to move
+        let other_side = [
+            Move::Place(0, 0),
+            Move::Place(0, 1),
+            Move::Place(1, 0),
+            Move::Pass,
+            Move::Place(1, 1),
+            Move::Place(0, 1),
+            Move::Place(0, 0),
+        ];
+        assert_eq!(play(KoRule::Simple, &other_side), Ok(()));
+        assert_eq!(
+            play(KoRule::PositionalSuperko, &other_side),
+            Err(MoveError::Ko)
+        );
+        assert_eq!(play(KoRule::SituationalSuperko, &other_side), Ok(()));
+
+        // Undo keeps the histories in step with the moves
+        let rules = Rules {
+            ko: KoRule::SituationalSuperko,
+            ..Rules::default()
+        };
+        let mut game = Game::with_rules(2, rules);
+        game.apply_move(Move::Place(0, 0)).unwrap();
+        game.apply_move(Move::Pass).unwrap();
+        assert!(game.undo());
+        assert_eq!(game.situation_history.len(), game.moves.len() + 1);
+        assert_eq!(game.board_history.len(), 2);
+    }
+
+    #[test]
+    fn test_ais_respect_superko() {
+        use ai::{Mcts, MonteCarloAI, RandomAI};
+        use player::Player;
+        use rules::{KoRule, Rules};
+
+        let rules = Rules {
+            ko: KoRule::PositionalSuperko,
+            ..Rules::default()
+        };
+        let mut game = Game::with_rules(2, rules);
+        for mv in [
+            Move::Place(0, 0),
+            Move::Place(0, 1),
+            Move::Place(1, 0),
+            Move::Place(1, 1),
+            Move::Place(0, 0),
+            Move::Place(1, 0),
+        ] {
+            game.apply_move(mv).unwrap();
+        }
+        // Black's only placement would recreate an earlier position
+        assert!(game.board.is_valid_move(0, 0, Stone::Black));
+        assert_eq!(game.legal_moves(), vec![Move::Pass]);
+
+        let mut players: Vec<Box<dyn Player>> = vec![
+            Box::new(RandomAI::new()),
+            Box::new(MonteCarloAI::new_with_millis(20)),
+            Box::new(Mcts::new_with_millis(20)),
+        ];
+        for player in &mut players {
+            assert_eq!(player.get_move(&game.view()), None, "{}", player.name());
+        }
+    }
+
+    #[test]
+    fn test_game_view() {
+        use gtp::GtpEngine;
+        use std::time::Duration;
+        use view::GameView;
+
+        let mut game = Game::new(5);
+        game.apply_move(Move::Place(2, 2)).unwrap();
+        game.apply_move(Move::Pass).unwrap();
+
+        let view = game.view();
+        assert_eq!(view.to_move, Stone::Black);
+        assert_eq!(view.last_move, Some(Move::Pass));
+        assert_eq!(view.consecutive_passes, 1);
+        assert_eq!(view.move_number, 2);
+        assert_eq!(view.board_history.len(), 2);
+        assert_eq!(view.situation_history.len(), 3);
+        assert_eq!(view.time_left, None);
+        assert!(!view.is_legal(Move::Place(2, 2)));
+        assert!(view.is_legal(Move::Place(0, 0)));
+
+        let board = Board::new(5);
+        let bare = GameView::new(&board, Stone::White);
+        assert!(bare.board_history.is_empty());
+        assert_eq!(bare.last_move, None);
+        assert!(bare.is_legal(Move::Place(0, 0)));
+
+        let mut engine = GtpEngine::new(5, Box::new(ai::RandomAI::new()));
+        assert!(engine.execute("time_left", &["W", "12.5", "0"]).is_ok());
+        assert!(engine.execute("time_left", &["W"]).is_err());
+        assert!(engine.execute("time_left", &["W", "inf", "0"]).is_err());
+        assert!(engine.execute("time_left", &["W", "1e30", "0"]).is_err());
+        assert_eq!(
+            engine.game().time_left(Stone::White),
+            Some(Duration::from_millis(12500))
+        );
+        engine.execute("play", &["B", "C3"]).unwrap();
+        assert_eq!(
+            engine.game().view().time_left,
+            Some(Duration::from_millis(12500))
+        );
+    }
+
+    #[test]
+    fn test_player_hooks() {
+        use ai::RandomAI;
+        use gtp::GtpEngine;
+        use observer::SilentObserver;
+        use player::Player;
+        use result::GameResult;
+        use rules::Rules;
+        use view::GameView;
+
+        // Plays randomly and remembers what it was told
+        #[derive(Default)]
+        struct Recorder {
+            inner: RandomAI,
+            games: Vec<usize>,
+            own_moves: usize,
+            opponent_moves: Vec<Move>,
+            result: Option<GameResult>,
+        }
+
+        impl Player for Recorder {
+            fn name(&self) -> &str {
+                "Recorder"
+            }
+
+            fn get_move(&mut self, view: &GameView) -> Option<(usize, usize)> {
+                self.own_moves += 1;
+                self.inner.get_move(view)
+            }
+
+            fn new_game(&mut self, board_size: usize, _rules: &Rules) {
+                self.games.push(board_size);
+            }
+
+            fn opponent_played(&mut self, mv: Move) {
+                self.opponent_moves.push(mv);
+            }
+
+            fn game_over(&mut self, result: &GameResult) {
+                self.result = Some(result.clone());
+            }
+        }
+
+        let mut black = Recorder::default();
+        let mut white = Recorder::default();
+        let mut game = Game::new(5);
+        game.max_moves = Some(20);
+        game.play_with(&mut black, &mut white, &mut SilentObserver);
+
+        assert_eq!(black.games, vec![5]);
+        assert_eq!(white.games, vec![5]);
+        let white_moves: Vec<Move> = game
+            .moves
+            .iter()
+            .filter(|(stone, _)| *stone == Stone::White)
+            .map(|&(_, mv)| mv)
+            .collect();
+        assert_eq!(black.opponent_moves, white_moves);
+        assert_eq!(
+            white.opponent_moves.len() + white_moves.len(),
+            game.moves.len()
+        );
+        assert!(black.own_moves >= game.moves.len() - white_moves.len());
+        assert_eq!(black.result, game.result());
+        assert_eq!(white.result, game.result());
+
+        let mut engine = GtpEngine::new(5, Box::new(Recorder::default()));
+        engine.execute("boardsize", &["7"]).unwrap();
+        engine.execute("play", &["B", "C3"]).unwrap();
+        assert!(engine.execute("play", &["W", "C3"]).is_err());
+        engine.execute("genmove", &["W"]).unwrap();
+        assert_eq!(engine.game().moves.len(), 2);
+    }
+
+    #[test]
+    fn test_mcts_tree_reuse() {
+        use ai::{Mcts, SearchBudget};
+        use player::Player;
+        use rules::Rules;
+
+        // A playout budget keeps the test independent of machine load
+        let mut mcts = Mcts::new_with_budget(SearchBudget::Playouts(2000)).with_seed(1);
+        let mut game = Game::new(5);
+        mcts.new_game(5, &Rules::default());
+
+        let first = mcts.get_move(&game.view()).unwrap();
+        assert_eq!(mcts.reused_visits(), 0);
+        game.apply_move(Move::Place(first.0, first.1)).unwrap();
+        let reply = game
+            .legal_moves()
+            .into_iter()
+            .find(|&mv| mv != Move::Pass)
+            .unwrap();
+        game.apply_move(reply).unwrap();
+
+        // The subtree under our move and the reply carries over
+        mcts.get_move(&game.view()).unwrap();
+        assert!(mcts.reused_visits() > 0);
+
+        // A new game starts from scratch
+        mcts.new_game(5, &Rules::default());
+        mcts.get_move(&game.view()).unwrap();
+        assert_eq!(mcts.reused_visits(), 0);
+    }
+
+    #[test]
+    fn test_parallel_mcts() {
+        use ai::{Mcts, Parallelism, SearchBudget};
+        use player::Player;
+
+        let game = Game::new(5);
+
+        // Every playout of every thread lands in the shared root
+        let mut mcts = Mcts::new_with_budget(SearchBudget::Playouts(1000)).with_threads(4);
+        let (x, y) = mcts.get_move(&game.view()).unwrap();
+        assert!(game.is_legal(Move::Place(x, y)));
+        let playouts = mcts.last_playouts();
+        assert!(playouts > 0);
+        mcts.get_move(&game.view()).unwrap();
+        assert_eq!(mcts.reused_visits() as u64, playouts);
+
+        let mut mcts = Mcts::new_with_budget(SearchBudget::Iterations(300))
+            .with_threads(3)
+            .with_parallelism(Parallelism::Root);
+        assert_eq!(mcts.name(), "MCTS AI (300 iterations, 3 root threads)");
+        let (x, y) = mcts.get_move(&game.view()).unwrap();
+        assert!(game.is_legal(Move::Place(x, y)));
+        assert!(mcts.last_playouts() > 0);
+        mcts.get_move(&game.view()).unwrap();
+        assert_eq!(mcts.reused_visits(), 0);
+
+        // A search the budget ends before any playout still plays a move
+        for parallelism in [Parallelism::Tree, Parallelism::Root] {
+            let mut mcts = Mcts::new_with_budget(SearchBudget::Playouts(0))
+                .with_threads(3)
+                .with_parallelism(parallelism);
+            let (x, y) = mcts.get_move(&game.view()).unwrap();
+            assert!(game.is_legal(Move::Place(x, y)));
+            assert_eq!(mcts.last_playouts(), 0);
+        }
+    }
+
+    #[test]
+    fn test_parallel_mcts_few_moves() {
+        use ai::{Mcts, SearchBudget};
+        use player::Player;
+
+        // With only a few moves, other threads expand all of the root's
+        // children before any playout has come back up to it
+        let mut game = Game::new(3);
+        for mv in [
+            Move::Place(0, 0),
+            Move::Pass,
+            Move::Place(1, 0),
+            Move::Pass,
+            Move::Place(2, 0),
+            Move::Pass,
+            Move::Place(0, 1),
+            Move::Pass,
+            Move::Place(2, 1),
+            Move::Pass,
+            Move::Place(1, 2),
+            Move::Pass,
+        ] {
+            game.apply_move(mv).unwrap();
+        }
+        for seed in 0..100 {
+            let mut mcts = Mcts::new_with_budget(SearchBudget::Playouts(200))
+                .with_threads(16)
+                .with_seed(seed);
+            if let Some((x, y)) = mcts.get_move(&game.view()) {
+                assert!(game.is_legal(Move::Place(x, y)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_rave_schedules() {
+        use ai::{Mcts, RaveSchedule, SearchBudget};
+        use player::Player;
+
+        let equivalence = RaveSchedule::Equivalence(300.0);
+        assert_eq!(equivalence.beta(0, 10), 1.0);
+        assert_eq!(equivalence.beta(100, 0), 0.0);
+        assert!((equivalence.beta(300, 50) - 0.5).abs() < 1e-9);
+        assert!(equivalence.beta(1000, 50) < equivalence.beta(100, 50));
+
+        let mse = RaveSchedule::MinimumMse(0.1);
+        assert_eq!(mse.beta(0, 10), 1.0);
+        assert!(mse.beta(50, 200) > mse.beta(500, 200));
+        assert_eq!(RaveSchedule::Off.beta(0, 10), 0.0);
+
+        let game = Game::new(5);
+        for rave in [RaveSchedule::Off, RaveSchedule::default(), mse] {
+            let mut mcts = Mcts::new_with_budget(SearchBudget::Playouts(300))
+                .with_rave(rave)
+                .with_seed(1);
+            let (x, y) = mcts.get_move(&game.view()).unwrap();
+            assert!(game.is_legal(Move::Place(x, y)));
+        }
+    }
+
+    #[test]
+    fn test_seeded_search_budgets() {
+        use ai::{Mcts, MonteCarloAI, Parallelism, RandomAI, SearchBudget};
+        use player::Player;
+        use std::time::Duration;
+
+        let mut game = Game::new(5);
+        game.apply_move(Move::Place(2, 2)).unwrap();
+        let view = game.view();
+
+        let random_moves = |seed| {
+            let mut ai = RandomAI::new().with_seed(seed);
+            (0..10).map(|_| ai.get_move(&view)).collect::<Vec<_>>()
+        };
+        assert_eq!(random_moves(7), random_moves(7));
+
+        let budget = SearchBudget::Playouts(300);
+        let mc_move = |seed| {
+            MonteCarloAI::new_with_budget(budget)
+                .with_seed(seed)
+                .get_move(&view)
+        };
+        assert_eq!(mc_move(7), mc_move(7));
+
+        let mut mcts = Mcts::new_with_budget(budget).with_seed(7);
+        assert_eq!(mcts.name(), "MCTS AI (300 playouts)");
+        let first = mcts.get_move(&view);
+        assert_eq!(mcts.last_playouts(), 300);
+        assert_eq!(
+            Mcts::new_with_budget(budget).with_seed(7).get_move(&view),
+            first
+        );
+
+        // Root-parallel trees get their share of the budget and their own
+        // seeds, so the merged result does not depend on thread timing
+        let parallel_move = || {
+            let mut mcts = Mcts::new_with_budget(SearchBudget::Iterations(301))
+                .with_threads(4)
+                .with_parallelism(Parallelism::Root)
+                .with_seed(11);
+            let mv = mcts.get_move(&view);
+            assert_eq!(mcts.last_playouts(), 301);
+            mv
+        };
+        assert_eq!(parallel_move(), parallel_move());
+
+        // A time budget past anything an Instant holds has no deadline
+        let countdown = ai::budget::Countdown::start(SearchBudget::Time(Duration::MAX));
+        assert!(!countdown.is_over());
+    }
+
+    #[test]
+    fn test_mcts_config() {
+        use ai::{Mcts, MctsConfig, MoveSelection, RaveSchedule, SearchBudget};
+        use player::Player;
+
+        let config = MctsConfig::new()
+            .with_exploration(0.7)
+            .with_rave(RaveSchedule::Off)
+            .with_max_playout_moves(10)
+            .with_eye_bonus(0)
+            .with_draw_margin(0)
+            .with_move_selection(MoveSelection::LowerConfidenceBound(1.96));
+        assert_eq!(config.exploration(), 0.7);
+        assert_eq!(config.max_playout_moves(5), 10);
+        assert_eq!(MctsConfig::default().max_playout_moves(5), 25);
+        assert_eq!(MctsConfig::default().eye_bonus(), 5);
+        assert_eq!(MctsConfig::default().draw_margin(), 2);
+
+        let mcts = Mcts::new_with_millis(10).with_config(config);
+        assert_eq!(mcts.config(), &config);
+        let mcts = mcts.with_rave(RaveSchedule::default());
+        assert_eq!(mcts.config().rave(), RaveSchedule::default());
+
+        let game = Game::new(5);
+        for selection in [
+            MoveSelection::MostVisits,
+            MoveSelection::HighestWinRate,
+            MoveSelection::LowerConfidenceBound(1.96),
+        ] {
+            let mut mcts = Mcts::new_with_budget(SearchBudget::Playouts(200))
+                .with_config(config.with_move_selection(selection))
+                .with_seed(3);
+            let (x, y) = mcts.get_move(&game.view()).unwrap();
+            assert!(game.is_legal(Move::Place(x, y)));
+        }
+    }
+
+    #[test]
+    fn test_mcts_passes() {
+        use ai::{Mcts, SearchBudget};
+        use player::Player;
+        use rules::Rules;
+
+        // Black owns the whole board bar one point, so after White's pass
+        // Black ends the game with a pass instead of filling its own eye
+        let rules = Rules {
+            komi: 0.5,
+            ..Rules::default()
+        };
+        let mut game = Game::with_rules(3, rules);
+        for mv in [
+            Move::Place(0, 0),
+            Move::Pass,
+            Move::Place(2, 0),
+            Move::Pass,
+            Move::Place(0, 2),
+            Move::Pass,
+            Move::Place(2, 2),
+            Move::Pass,
+            Move::Place(1, 0),
+            Move::Pass,
+            Move::Place(0, 1),
+            Move::Pass,
+            Move::Place(2, 1),
+            Move::Pass,
+        ] {
+            game.apply_move(mv).unwrap();
+        }
+        let mut mcts = Mcts::new_with_budget(SearchBudget::Playouts(500)).with_seed(5);
+        assert_eq!(mcts.get_move(&game.view()), None);
+
+        // A pass by White would let Black end the game ahead, so White
+        // plays on
+        let mut game = Game::with_rules(5, rules);
+        game.apply_move(Move::Place(2, 2)).unwrap();
+        let mut mcts = Mcts::new_with_budget(SearchBudget::Playouts(500)).with_seed(5);
+        assert!(mcts.get_move(&game.view()).is_some());
+    }
+
+    #[test]
+    fn test_mcts_transpositions() {
+        use ai::{Mcts, MctsConfig, Parallelism, SearchBudget};
+        use player::Player;
+
+        let game = Game::new(5);
+        let config = MctsConfig::new().with_transpositions(10_000);
+        let mut mcts = Mcts::new_with_budget(SearchBudget::Playouts(2000))
+            .with_config(config)
+            .with_seed(1);
+        let (x, y) = mcts.get_move(&game.view()).unwrap();
+        assert!(game.is_legal(Move::Place(x, y)));
+        assert!(mcts.transposition_hits() > 0);
+
+        let mut plain = Mcts::new_with_budget(SearchBudget::Playouts(2000)).with_seed(1);
+        plain.get_move(&game.view()).unwrap();
+        assert_eq!(plain.transposition_hits(), 0);
+
+        assert!(mcts.table_nodes() > 26);
+
+        // The limit caps the nodes, and with no room past the root's
+        // children the search still finds a move
+        let mut small = Mcts::new_with_budget(SearchBudget::Playouts(2000))
+            .with_config(MctsConfig::new().with_transpositions(100))
+            .with_seed(1);
+        small.get_move(&game.view()).unwrap();
+        assert!(small.table_nodes() <= 100);
+        let mut tiny = Mcts::new_with_budget(SearchBudget::Playouts(500))
+            .with_config(MctsConfig::new().with_transpositions(1))
+            .with_seed(1);
+        tiny.get_move(&game.view()).unwrap();
+        assert_eq!(tiny.transposition_hits(), 0);
+        assert_eq!(tiny.table_nodes(), 27);
+
+        let mut mcts = Mcts::new_with_budget(SearchBudget::Playouts(400))
+            .with_config(config)
+            .with_threads(2)
+            .with_parallelism(Parallelism::Root)
+            .with_seed(1);
+        let (x, y) = mcts.get_move(&game.view()).unwrap();
+        assert!(game.is_legal(Move::Place(x, y)));
+    }
+
+    // Plays the transposition DAG against the plain tree at equal playouts;
+    // run with --ignored --nocapture to see the score
+    #[test]
+    #[ignore = "Strength comparison, takes a while"]
+    fn test_transposition_strength() {
+        use ai::{Mcts, MctsConfig, SearchBudget};
+        use observer::SilentObserver;
+
+        let games = 20;
+        let budget = SearchBudget::Playouts(1000);
+        let mut dag_score = 0.0;
+        for i in 0..games {
+            let mut dag = Mcts::new_with_budget(budget)
+                .with_config(MctsConfig::new().with_transpositions(1 << 20))
+                .with_seed(i);
+            let mut plain = Mcts::new_with_budget(budget).with_seed(i + 1000);
+            let mut game = Game::new(5);
+            game.max_moves = Some(75);
+            let dag_color = if i % 2 == 0 {
+                Stone::Black
+            } else {
+                Stone::White
+            };
+            match dag_color {
+                Stone::Black => game.play_with(&mut dag, &mut plain, &mut SilentObserver),
+                Stone::White => game.play_with(&mut plain, &mut dag, &mut SilentObserver),
+            }
+            dag_score += match game.result().unwrap().winner {
+                Some(winner) if winner == dag_color => 1.0,
+                Some(_) => 0.0,
+                None => 0.5,
+            };
+        }
+        println!("Transpositions vs plain tree: {}/{}", dag_score, games);
+        assert!(dag_score >= games as f64 * 0.25);
+    }
+
+    #[test]
+    fn test_search_analysis() {
+        use ai::{Mcts, MctsConfig, MonteCarloAI, MoveSelection, Parallelism, SearchBudget};
+        use player::Player;
+        use std::time::Duration;
+
+        let game = Game::new(5);
+        let mut mcts = Mcts::new_with_budget(SearchBudget::Playouts(1000)).with_seed(3);
+        let analysis = mcts
+            .analyze(&game.view(), Duration::MAX, &mut |_| {
+                panic!("no report due")
+            })
+            .unwrap();
+        assert_eq!(analysis.to_move, Stone::Black);
+        assert_eq!(analysis.visits, 1000);
+        let visits: u32 = analysis.candidates.iter().map(|c| c.visits).sum();
+        assert!(visits <= analysis.visits);
+        assert!(analysis
+            .candidates
+            .windows(2)
+            .all(|w| w[0].visits >= w[1].visits));
+        for candidate in &analysis.candidates {
+            assert_eq!(candidate.pv[0], candidate.mv);
+            assert!((0.0..=1.0).contains(&candidate.win_rate));
+            assert!((candidate.prior - 1.0 / 26.0).abs() < 1e-9);
+        }
+        assert!(analysis.candidates[0].pv.len() > 1);
+
+        // The analysed move is the one get_move picks from the same search
+        let config = MctsConfig::new().with_move_selection(MoveSelection::HighestWinRate);
+        let mut a = Mcts::new_with_budget(SearchBudget::Playouts(300))
+            .with_config(config)
+            .with_seed(5);
+        let mut b = Mcts::new_with_budget(SearchBudget::Playouts(300))
+            .with_config(config)
+            .with_seed(5);
+        let analysis = a.analyze(&game.view(), Duration::MAX, &mut |_| {}).unwrap();
+        assert_eq!(analysis.best_move().to_option(), b.get_move(&game.view()));
+
+        // Without a single simulation the Monte Carlo AI still plays a move
+        let mut mc = MonteCarloAI::new_with_budget(SearchBudget::Playouts(0));
+        let analysis = mc
+            .analyze(&game.view(), Duration::MAX, &mut |_| {})
+            .unwrap();
+        assert_eq!(analysis.visits, 0);
+        assert_eq!(analysis.candidates[0].visits, 0);
+        assert_eq!(mc.get_move(&game.view()), Some((0, 0)));
+
+        // Reports stream in while the search runs
+        for mut engine in [
+            Box::new(Mcts::new_with_millis(200)) as Box<dyn Player>,
+            Box::new(
+                Mcts::new_with_millis(200)
+                    .with_threads(2)
+                    .with_parallelism(Parallelism::Root),
+            ),
+            Box::new(MonteCarloAI::new_with_millis(200)),
+        ] {
+            let mut reports = Vec::new();
+            let analysis = engine
+                .analyze(&game.view(), Duration::from_millis(40), &mut |analysis| {
+                    reports.push(analysis.clone())
+                })
+                .unwrap();
+            assert!(reports.len() >= 2, "{}", engine.name());
+            assert!(reports.windows(2).all(|w| w[0].visits <= w[1].visits));
+            assert!(reports.last().unwrap().visits <= analysis.visits);
+            assert!(!analysis.candidates.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_gtp_lz_analyze() {
+        use ai::{Mcts, RandomAI, SearchBudget};
+        use gtp::GtpEngine;
+
+        let mcts = Mcts::new_with_budget(SearchBudget::Playouts(200)).with_seed(1);
+        let mut engine = GtpEngine::new(5, Box::new(mcts));
+        let input = "1 lz-analyze b 0\n2 lz-genmove_analyze w interval 0\nlz-analyze x y\n";
+        let mut output = Vec::new();
+        engine.run(input.as_bytes(), &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let responses: Vec<&str> = output.split("\n\n").collect();
+        assert_eq!(responses.len(), 4);
+        assert!(responses[0].starts_with("=1 \ninfo move "));
+        assert!(responses[0].contains(" winrate ") && responses[0].contains(" order 0 pv "));
+        let lines: Vec<&str> = responses[1].lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "=2 ");
+        assert!(lines[1].starts_with("info move "));
+        assert!(lines[2].starts_with("play "));
+        assert_eq!(responses[2], "? syntax error");
+        // lz-analyze only looks; lz-genmove_analyze played White's move
+        assert_eq!(engine.game().board.count_stones(), (0, 1));
+
+        // With an interval, info lines stream in before the final one
+        let mut engine = GtpEngine::new(5, Box::new(Mcts::new_with_millis(300)));
+        let mut output = Vec::new();
+        engine
+            .run("lz-analyze b 5\n".as_bytes(), &mut output)
+            .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("=\ninfo move ") && output.ends_with("\n\n"));
+        assert!(
+            output
+                .lines()
+                .filter(|l| l.starts_with("info move "))
+                .count()
+                >= 3
+        );
+
+        // Engines without a search still answer lz-genmove_analyze
+        let mut engine = GtpEngine::new(5, Box::new(RandomAI::new()));
+        assert!(engine.execute("lz-analyze", &["b"]).unwrap().is_empty());
+        let response = engine.execute("lz-genmove_analyze", &["b"]).unwrap();
+        assert!(response.starts_with("\nplay "));
+        assert_eq!(engine.game().board.count_stones(), (1, 0));
+    }
+
+    #[test]
+    fn test_playout_policies() {
+        use ai::{HeavyPolicy, LightPolicy, PlayoutPolicy, RandomPolicy};
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let mut rng = StdRng::seed_from_u64(1);
+        let heavy = HeavyPolicy;
+
+        // White's last stone is left in atari: take it
+        let mut board = Board::new(5);
+        for (x, y, stone) in [
+            (0, 1, Stone::Black),
+            (1, 0, Stone::Black),
+            (2, 1, Stone::Black),
+            (1, 1, Stone::White),
+        ] {
+            board.place_stone(x, y, stone).unwrap();
+        }
+        let mv = heavy.pick_move(&board, Stone::Black, Some((1, 1)), &mut rng);
+        assert_eq!(mv, Some((1, 2)));
+
+        // White puts a black stone in atari: extend it
+        let mut board = Board::new(5);
+        for (x, y, stone) in [
+            (2, 2, Stone::Black),
+            (1, 2, Stone::White),
+            (3, 2, Stone::White),
+            (2, 1, Stone::White),
+        ] {
+            board.place_stone(x, y, stone).unwrap();
+        }
+        assert_eq!(board.liberties(2, 2), vec![(2, 3)]);
+        let mv = heavy.pick_move(&board, Stone::Black, Some((2, 1)), &mut rng);
+        assert_eq!(mv, Some((2, 3)));
+
+        // A hane answers White's stone between two black ones
+        let mut board = Board::new(5);
+        for (x, y, stone) in [
+            (1, 1, Stone::Black),
+            (3, 1, Stone::Black),
+            (2, 1, Stone::White),
+        ] {
+            board.place_stone(x, y, stone).unwrap();
+        }
+        let mut picked = Vec::new();
+        for _ in 0..50 {
+            let (x, y) = heavy
+                .pick_move(&board, Stone::Black, Some((2, 1)), &mut rng)
+                .unwrap();
+            assert!(x.abs_diff(2) <= 1 && y.abs_diff(1) <= 1);
+            picked.push((x, y));
+        }
+        assert!(picked.contains(&(2, 2)));
+
+        // Without a last move all policies play anywhere but in own eyes
+        let mut board = Board::new(3);
+        for (x, y) in [(1, 0), (0, 1), (1, 1)] {
+            board.place_stone(x, y, Stone::Black).unwrap();
+        }
+        for policy in [&LightPolicy as &dyn PlayoutPolicy, &RandomPolicy, &heavy] {
+            for _ in 0..20 {
+                let mv = policy.pick_move(&board, Stone::Black, None, &mut rng);
+                assert_ne!(mv, Some((0, 0)));
+            }
+        }
+
+        // With only its last two eyes left a player passes, but light
+        // playouts fill one as they always have
+        let mut board = Board::new(3);
+        for (x, y) in [(1, 0), (2, 0), (0, 1), (1, 1), (2, 1), (0, 2), (1, 2)] {
+            board.place_stone(x, y, Stone::Black).unwrap();
+        }
+        for policy in [&RandomPolicy as &dyn PlayoutPolicy, &heavy] {
+            assert_eq!(policy.pick_move(&board, Stone::Black, None, &mut rng), None);
+        }
+        let mv = LightPolicy.pick_move(&board, Stone::Black, None, &mut rng);
+        assert!(mv == Some((0, 0)) || mv == Some((2, 2)));
+    }
+
+    #[test]
+    fn test_heavy_playout_engines() {
+        use ai::{HeavyPolicy, Mcts, MonteCarloAI, RandomAI, SearchBudget};
+        use player::Player;
+        use rules::Rules;
+        use stats::run_match;
+
+        let game = Game::new(5);
+        let mut mcts = Mcts::new_with_budget(SearchBudget::Playouts(300))
+            .with_playout_policy(HeavyPolicy)
+            .with_seed(1);
+        assert_eq!(mcts.name(), "MCTS AI (300 playouts, heavy playouts)");
+        let (x, y) = mcts.get_move(&game.view()).unwrap();
+        assert!(game.is_legal(Move::Place(x, y)));
+
+        let mut mc = MonteCarloAI::new_with_budget(SearchBudget::Playouts(300))
+            .with_playout_policy(HeavyPolicy)
+            .with_seed(1);
+        assert_eq!(mc.name(), "Monte Carlo AI (300 playouts, heavy playouts)");
+        let (x, y) = mc.get_move(&game.view()).unwrap();
+        assert!(game.is_legal(Move::Place(x, y)));
+
+        let mut a = RandomAI::new().with_seed(1);
+        let mut b = RandomAI::new().with_seed(2);
+        let stats = run_match(&mut a, &mut b, 5, 6, Rules::default(), Some(60));
+        assert_eq!(stats.games(), 6);
+        assert!((0.0..=1.0).contains(&stats.score()));
+    }
+
+    // Plays heavy playouts against light ones at equal playouts; run with
+    // --ignored --nocapture to see the score
+    #[test]
+    #[ignore = "Strength comparison, takes a while"]
+    fn test_heavy_playout_strength() {
+        use ai::{HeavyPolicy, Mcts, SearchBudget};
+        use rules::Rules;
+        use stats::run_match;
+
+        let budget = SearchBudget::Playouts(1000);
+        let mut heavy = Mcts::new_with_budget(budget)
+            .with_playout_policy(HeavyPolicy)
+            .with_seed(1);
+        let mut light = Mcts::new_with_budget(budget).with_seed(2);
+        let stats = run_match(&mut heavy, &mut light, 5, 20, Rules::default(), Some(75));
+        println!(
+            "Heavy vs light playouts: {}-{}-{} ({:.0}%)",
+            stats.wins,
+            stats.losses,
+            stats.draws,
+            stats.score() * 100.0
+        );
+        assert!(stats.score() >= 0.25);
+    }
+
+    #[test]
+    fn test_monte_carlo_allocation() {
+        use ai::{MonteCarloAI, MoveAllocation, SearchBudget};
+        use player::Player;
+        use std::time::Duration;
+
+        assert_eq!("ucb1".parse(), Ok(MoveAllocation::Ucb1(2f64.sqrt())));
+        assert_eq!("halving".parse(), Ok(MoveAllocation::SuccessiveHalving));
+        assert!("greedy".parse::<MoveAllocation>().is_err());
+
+        let game = Game::new(5);
+        let moves = 25;
+        for allocation in [
+            MoveAllocation::RoundRobin,
+            MoveAllocation::Ucb1(2f64.sqrt()),
+            MoveAllocation::SuccessiveHalving,
+        ] {
+            let mut mc = MonteCarloAI::new_with_budget(SearchBudget::Iterations(3))
+                .with_allocation(allocation)
+                .with_seed(1);
+            let analysis = mc
+                .analyze(&game.view(), Duration::MAX, &mut |_| {})
+                .unwrap();
+            assert_eq!(analysis.visits, 3 * moves);
+
+            let mut mc = MonteCarloAI::new_with_budget(SearchBudget::Playouts(2000))
+                .with_allocation(allocation)
+                .with_seed(1);
+            assert_eq!(mc.allocation(), allocation);
+            let analysis = mc
+                .analyze(&game.view(), Duration::MAX, &mut |_| {})
+                .unwrap();
+            let most = analysis.candidates.iter().map(|c| c.visits).max().unwrap();
+            let least = analysis.candidates.iter().map(|c| c.visits).min().unwrap();
+            if allocation == MoveAllocation::RoundRobin {
+                assert!(most - least <= 1);
+            } else {
+                // The simulations pile up on the move that is picked
+                assert_eq!(analysis.candidates[0].visits, most);
+                assert!(most >= 2 * least);
+            }
+
+            // get_move plays the first candidate of the same search
+            let mut a = MonteCarloAI::new_with_budget(SearchBudget::Playouts(300))
+                .with_allocation(allocation)
+                .with_seed(2);
+            let mut b = MonteCarloAI::new_with_budget(SearchBudget::Playouts(300))
+                .with_allocation(allocation)
+                .with_seed(2);
+            let analysis = b.analyze(&game.view(), Duration::MAX, &mut |_| {}).unwrap();
+            assert_eq!(a.get_move(&game.view()), analysis.best_move().to_option());
+        }
+
+        let mc = MonteCarloAI::new_with_budget(SearchBudget::Playouts(200))
+            .with_allocation(MoveAllocation::Ucb1(1.0));
+        assert_eq!(mc.name(), "Monte Carlo AI (200 playouts, UCB1)");
+        let mc = MonteCarloAI::new_with_budget(SearchBudget::Playouts(200));
+        assert_eq!(mc.name(), "Monte Carlo AI (200 playouts)");
+    }
+
+    #[test]
+    fn test_tournament() {
+        use ai::{MonteCarloAI, RandomAI, SearchBudget};
+        use tournament::{Format, Tournament};
+
+        let tournament = Tournament::new(Format::RoundRobin)
+            .with_entrant("Random 1", || Box::new(RandomAI::new().with_seed(1)))
+            .with_entrant("Random 2", || Box::new(RandomAI::new().with_seed(2)))
+            .with_entrant("Monte Carlo", || {
+                Box::new(MonteCarloAI::new_with_budget(SearchBudget::Playouts(10)).with_seed(3))
+            })
+            .with_games(2)
+            .with_board_sizes(&[4, 5])
+            .with_max_moves(60);
+        assert_eq!(tournament.pairings(), vec![(0, 1), (0, 2), (1, 2)]);
+
+        let table = tournament.run();
+        for a in 0..3 {
+            assert_eq!(table.total(a).games(), 8);
+            for b in 0..3 {
+                match table.result(a, b) {
+                    None => assert_eq!(a, b),
+                    Some(stats) => {
+                        assert_eq!(stats.games(), 4);
+                        assert_eq!(table.result(b, a), Some(stats.reversed()));
+                    }
+                }
+            }
+        }
+        let text = table.to_string();
+        assert_eq!(text.lines().count(), 4);
+        assert!(text.lines().nth(3).unwrap().contains("Monte Carlo"));
+
+        // A gauntlet only pairs the first entrant with the others
+        let gauntlet = Tournament::new(Format::Gauntlet)
+            .with_entrant("A", || Box::new(RandomAI::new().with_seed(4)))
+            .with_entrant("B", || Box::new(RandomAI::new().with_seed(5)))
+            .with_entrant("C", || Box::new(RandomAI::new().with_seed(6)))
+            .with_games(3)
+            .with_board_sizes(&[5]);
+        let table = gauntlet.run();
+        assert_eq!(table.result(0, 1).unwrap().games(), 3);
+        assert_eq!(table.result(2, 0).unwrap().games(), 3);
+        assert_eq!(table.result(1, 2), None);
+        assert_eq!(table.total(0).games(), 6);
+
+        // Records name the entrants and number the games across pairings
+        let (recorded, records) = gauntlet.run_recorded();
+        assert_eq!(recorded.total(0).games(), 6);
+        assert_eq!(records.len(), 6);
+        assert!(records.iter().enumerate().all(|(i, r)| r.game == i as u32));
+        assert!(records.iter().all(|r| r.board_size == 5));
+        assert_eq!(
+            (records[0].black.as_str(), records[0].white.as_str()),
+            ("A", "B")
+        );
+        assert_eq!(
+            (records[1].black.as_str(), records[1].white.as_str()),
+            ("B", "A")
+        );
+        assert_eq!(
+            (records[3].black.as_str(), records[3].white.as_str()),
+            ("A", "C")
+        );
+    }
+
+    #[test]
+    fn test_ratings() {
+        use rating::{elo_from_score, expected_score, match_elo, Ratings};
+        use stats::MatchStats;
+        use tournament::CrossTable;
+
+        assert_eq!(elo_from_score(0.5), 0.0);
+        assert!((elo_from_score(0.75) - 190.85).abs() < 0.01);
+        assert!((expected_score(elo_from_score(0.3)) - 0.3).abs() < 1e-9);
+
+        let even = match_elo(MatchStats {
+            wins: 40,
+            losses: 40,
+            draws: 20,
+        });
+        assert_eq!(even.elo, 0.0);
+        assert!(even.margin > 30.0 && even.margin < 100.0);
+        let stats = |wins, losses| MatchStats {
+            wins,
+            losses,
+            draws: 0,
+        };
+        assert!(match_elo(stats(400, 400)).margin < even.margin);
+
+        let names = ["Random", "Strong", "Stronger", "Loner"];
+        let mut table = CrossTable::new(names.iter().map(|n| n.to_string()).collect());
+        table.record(1, 0, stats(15, 5));
+        table.record(2, 0, stats(20, 0));
+        table.record(2, 1, stats(14, 6));
+        let ratings = Ratings::estimate(&table, 0);
+        let order: Vec<_> = ratings.ratings().iter().map(|r| r.entrant).collect();
+        assert_eq!(order, vec![2, 1, 0]);
+        let random = ratings.rating(0).unwrap();
+        assert_eq!(random.estimate.elo, 0.0);
+        assert_eq!(random.estimate.margin, 0.0);
+        // The prior keeps the perfect score finite
+        let stronger = ratings.rating(2).unwrap().estimate;
+        assert!(stronger.elo.is_finite() && stronger.margin.is_finite());
+        assert!(stronger.elo > ratings.rating(1).unwrap().estimate.elo);
+        assert!(ratings.rating(1).unwrap().estimate.margin > 0.0);
+        // Nobody played the loner, so it has no rating
+        assert!(ratings.rating(3).is_none());
+        assert_eq!(ratings.to_string().lines().count(), 4);
+
+        // Evenly matched players end up level with the anchor
+        let mut table = CrossTable::new(vec!["A".to_string(), "B".to_string()]);
+        table.record(0, 1, stats(10, 10));
+        let ratings = Ratings::estimate(&table, 1);
+        assert!(ratings.rating(0).unwrap().estimate.elo.abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_sprt() {
+        use ai::{MonteCarloAI, RandomAI, SearchBudget};
+        use rules::Rules;
+        use stats::{run_sprt, MatchStats, Sprt, SprtDecision};
+
+        let sprt = Sprt::new(0.0, 50.0)
+            .and_then(|sprt| sprt.with_errors(0.05, 0.1))
+            .unwrap();
+        let (lower, upper) = sprt.bounds();
+        assert!((lower - (0.1f64 / 0.95).ln()).abs() < 1e-12);
+        assert!((upper - 18.0f64.ln()).abs() < 1e-12);
+
+        let stats = |wins, losses, draws| MatchStats {
+            wins,
+            losses,
+            draws,
+        };
+        assert_eq!(sprt.llr(&MatchStats::default()), 0.0);
+        assert!(sprt.llr(&stats(60, 40, 0)) > 0.0);
+        assert!(sprt.llr(&stats(40, 60, 0)) < 0.0);
+        assert!(sprt.llr(&stats(600, 400, 0)) > sprt.llr(&stats(60, 40, 0)));
+        assert!(sprt.llr(&stats(5, 0, 0)) > 0.0);
+        assert_eq!(sprt.decision(&stats(5, 5, 0)), None);
+        assert_eq!(
+            sprt.decision(&stats(700, 300, 0)),
+            Some(SprtDecision::AcceptH1)
+        );
+        assert_eq!(
+            sprt.decision(&stats(450, 550, 0)),
+            Some(SprtDecision::AcceptH0)
+        );
+
+        // Monte Carlo is far stronger than random play, which the test
+        // finds out long before its game limit
+        let mut mc = MonteCarloAI::new_with_budget(SearchBudget::Playouts(100)).with_seed(1);
+        let mut random = RandomAI::new().with_seed(2);
+        let mut reports = Vec::new();
+        let outcome = run_sprt(
+            &mut mc,
+            &mut random,
+            5,
+            Sprt::new(0.0, 200.0).unwrap().with_max_games(100),
+            Rules::default(),
+            Some(60),
+            &mut |record, stats, llr| {
+                assert_eq!(record.game + 1, stats.games());
+                reports.push((stats.games(), llr))
+            },
+        );
+        assert_eq!(outcome.decision, Some(SprtDecision::AcceptH1));
+        assert!(outcome.stats.games() < 100 && outcome.stats.games().is_multiple_of(2));
+        assert_eq!(reports.len() as u32, outcome.stats.games());
+        assert_eq!(reports.last().unwrap().1, outcome.llr);
+
+        // Without a decision the match ends at its limit
+        let mut a = RandomAI::new().with_seed(3);
+        let mut b = RandomAI::new().with_seed(4);
+        let outcome = run_sprt(
+            &mut a,
+            &mut b,
+            5,
+            Sprt::new(0.0, 5.0).unwrap().with_max_games(6),
+            Rules::default(),
+            Some(60),
+            &mut |_, _, _| {},
+        );
+        assert_eq!(outcome.stats.games(), 6);
+        assert_eq!(outcome.decision, None);
+
+        // Bounds no result could ever cross are refused
+        assert!(Sprt::new(0.0, 0.0).is_err());
+        assert!(Sprt::new(10.0, 0.0).is_err());
+        assert!(Sprt::new(0.0, f64::NAN).is_err());
+        let sprt = Sprt::new(0.0, 5.0).unwrap();
+        assert!(sprt.with_errors(0.0, 0.05).is_err());
+        assert!(sprt.with_errors(0.05, 1.0).is_err());
+    }
+
+    #[test]
+    fn test_parallel_statistics() {
+        use rules::Rules;
+        use stats::{run_game_seeded, run_statistics_with};
+        use std::time::Duration;
+
+        let first = run_game_seeded(5, Rules::default(), 7);
+        assert_eq!(run_game_seeded(5, Rules::default(), 7), first);
+
+        // The same seed gives the same results on any number of threads
+        let one = run_statistics_with(5, 40, Rules::default(), 1, 3, &mut |_| {});
+        let mut reported = 0;
+        let four = run_statistics_with(5, 40, Rules::default(), 4, 3, &mut |_| reported += 1);
+        assert_eq!(reported, 40);
+        assert_eq!(one.black_wins + one.white_wins + one.draws, 40);
+        assert_eq!(
+            (one.black_wins, one.white_wins, one.draws),
+            (four.black_wins, four.white_wins, four.draws)
+        );
+        assert_eq!(one.total_moves, four.total_moves);
+        assert_eq!(one.total_black_score, four.total_black_score);
+        assert_eq!(one.total_white_score, four.total_white_score);
+        assert!(one.elapsed > Duration::ZERO);
+    }
+
+    #[test]
+    fn test_record_export() {
+        use ai::RandomAI;
+        use export::{write_match_summary, write_stats_summary, RecordFormat, RecordWriter};
+        use rules::Rules;
+        use stats::{play_random_games, run_match_recorded, GameStats};
+
+        assert_eq!("csv".parse(), Ok(RecordFormat::Csv));
+        assert_eq!("jsonl".parse(), Ok(RecordFormat::JsonLines));
+        assert!("xml".parse::<RecordFormat>().is_err());
+
+        let records = play_random_games(5, 3, Rules::default(), 2, 11, &mut |_| {});
+        assert_eq!(
+            records.iter().map(|r| r.game).collect::<Vec<_>>(),
+            [0, 1, 2]
+        );
+        assert_eq!(records[1].seed, Some(12));
+
+        let mut csv = RecordWriter::new(Vec::new(), RecordFormat::Csv);
+        csv.write_all(&records).unwrap();
+        let csv = String::from_utf8(csv.into_inner()).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("game,black,white,board_size,winner,result,margin"));
+        assert!(lines[0].ends_with("duration_ms,seed"));
+        assert!(lines[1].starts_with("0,Random AI,Random AI,5,"));
+        assert!(lines[1].ends_with(",11"));
+
+        let mut jsonl = RecordWriter::new(Vec::new(), RecordFormat::JsonLines);
+        jsonl.write_all(&records).unwrap();
+        let jsonl = String::from_utf8(jsonl.into_inner()).unwrap();
+        assert_eq!(jsonl.lines().count(), 3);
+        let first = jsonl.lines().next().unwrap();
+        assert!(first.starts_with(r#"{"game":0,"black":"Random AI","white":"Random AI","#));
+        assert!(first.ends_with(r#""seed":11}"#));
+        let moves = format!(r#""moves":{},"#, records[0].result.move_count);
+        assert!(first.contains(&moves));
+
+        // Scores an infinite komi leaves without a number are null
+        let mut record = records[0].clone();
+        record.result.margin = f64::INFINITY;
+        record.result.white.total = f64::INFINITY;
+        let mut jsonl = RecordWriter::new(Vec::new(), RecordFormat::JsonLines);
+        jsonl.write(&record).unwrap();
+        let line = String::from_utf8(jsonl.into_inner()).unwrap();
+        assert!(line.contains(r#""margin":null,"#));
+        assert!(line.contains(r#""white_score":null,"#));
+
+        // Match games have players on both colors and no seed; names with
+        // separators or quotes are escaped
+        let mut a = RandomAI::new().with_seed(1);
+        let mut b = RandomAI::new().with_seed(2);
+        let (stats, records) = run_match_recorded(&mut a, &mut b, 5, 2, Rules::default(), None);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].seed, None);
+        let mut out = Vec::new();
+        let matches = [("A, \"new\"", "B", stats), ("B", "C", stats.reversed())];
+        write_match_summary(&mut out, RecordFormat::Csv, &matches).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("player,opponent,games,wins,losses,draws,score,elo,elo_margin\n"));
+        assert!(out.contains("\n\"A, \"\"new\"\"\",B,2,"));
+        assert_eq!(out.lines().count(), 3);
+        let mut out = Vec::new();
+        write_match_summary(&mut out, RecordFormat::JsonLines, &[("A\\B", "C", stats)]).unwrap();
+        assert!(String::from_utf8(out)
+            .unwrap()
+            .starts_with(r#"{"player":"A\\B","#));
+
+        let mut stats = GameStats::new();
+        for record in &play_random_games(5, 4, Rules::default(), 1, 5, &mut |_| {}) {
+            stats.record(&record.result, record.duration);
+        }
+        let mut out = Vec::new();
+        write_stats_summary(&mut out, RecordFormat::JsonLines, &stats, 5).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.lines().count(), 1);
+        assert!(out.starts_with(r#"{"board_size":5,"games":4,"#));
+    }
 }
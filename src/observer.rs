@@ -0,0 +1,66 @@
+use crate::board::Stone;
+use crate::game::{Game, Move, MoveError, MoveOutcome};
+use crate::player::Player;
+
+// Receives the events of Game::play_with. Every method defaults to doing
+// nothing, so observers only implement what they need.
+pub trait GameObserver {
+    fn game_started(&mut self, _game: &Game, _black: &dyn Player, _white: &dyn Player) {}
+    fn turn_started(&mut self, _game: &Game, _player: &dyn Player) {}
+    fn move_played(&mut self, _game: &Game, _player: &dyn Player, _outcome: &MoveOutcome) {}
+    fn move_rejected(&mut self, _game: &Game, _player: &dyn Player, _mv: Move, _error: MoveError) {}
+    fn game_ended(&mut self, _game: &Game) {}
+}
+
+// Plays games without any output
+pub struct SilentObserver;
+
+impl GameObserver for SilentObserver {}
+
+// Prints the board and every move to stdout
+pub struct ConsoleRenderer;
+
+impl GameObserver for ConsoleRenderer {
+    fn game_started(&mut self, _game: &Game, black: &dyn Player, white: &dyn Player) {
+        println!("Game Start!");
+        println!("Black: {}", black.name());
+        println!("White: {}", white.name());
+        println!();
+    }
+
+    fn turn_started(&mut self, game: &Game, player: &dyn Player) {
+        println!("{}", game.board);
+        println!("{}'s turn ({})", player.name(), game.current_turn);
+    }
+
+    fn move_played(&mut self, _game: &Game, player: &dyn Player, outcome: &MoveOutcome) {
+        match outcome.mv {
+            Move::Place(..) => println!("{} plays at {}", player.name(), outcome.mv),
+            Move::Pass => println!("{} passes", player.name()),
+        }
+        if !outcome.game_over {
+            println!();
+        }
+    }
+
+    fn move_rejected(&mut self, _game: &Game, _player: &dyn Player, _mv: Move, error: MoveError) {
+        println!("Invalid move: {}", error);
+    }
+
+    fn game_ended(&mut self, game: &Game) {
+        println!("\n=== Game Over ===");
+        println!("{}", game.board);
+
+        let result = game.evaluate();
+
+        println!("Final Score ({}):", result.scoring);
+        println!("Black: {}", result.breakdown(Stone::Black));
+        println!("White: {}", result.breakdown(Stone::White));
+
+        match result.winner {
+            Some(Stone::Black) => println!("\nBlack wins by {} points!", result.margin),
+            Some(Stone::White) => println!("\nWhite wins by {} points!", result.margin),
+            None => println!("\nThe game is a draw!"),
+        }
+    }
+}
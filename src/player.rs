@@ -1,9 +1,36 @@
-use crate::board::{Board, Stone};
+use crate::analysis::Analysis;
+use crate::game::Move;
+use crate::result::GameResult;
+use crate::rules::Rules;
+use crate::view::GameView;
 use std::io::{self, Write};
+use std::time::Duration;
 
+// Players are told about the game as it goes so they can keep state
+// between moves. Every hook defaults to doing nothing.
 pub trait Player {
     fn name(&self) -> &str;
-    fn get_move(&self, board: &Board, stone: Stone) -> Option<(usize, usize)>;
+    // Picks a move for `view.to_move`, or None to pass
+    fn get_move(&mut self, view: &GameView) -> Option<(usize, usize)>;
+
+    // Searches like get_move, calling `report` with the analysis so far
+    // about every `interval` (never, if Duration::MAX), and returns the
+    // final analysis. Players without a search return None and are asked
+    // for a move instead.
+    fn analyze(
+        &mut self,
+        _view: &GameView,
+        _interval: Duration,
+        _report: &mut dyn FnMut(&Analysis),
+    ) -> Option<Analysis> {
+        None
+    }
+
+    // Called before the first move of a game is asked for
+    fn new_game(&mut self, _board_size: usize, _rules: &Rules) {}
+    // The other side's move, passes included, once the game accepted it
+    fn opponent_played(&mut self, _mv: Move) {}
+    fn game_over(&mut self, _result: &GameResult) {}
 }
 
 pub struct HumanPlayer {
@@ -29,7 +56,8 @@ impl Player for HumanPlayer {
         &self.name
     }
 
-    fn get_move(&self, board: &Board, _stone: Stone) -> Option<(usize, usize)> {
+    fn get_move(&mut self, view: &GameView) -> Option<(usize, usize)> {
+        let board = view.board;
         loop {
             print!("Enter your move (e.g., 'D4' or 'pass'): ");
             io::stdout().flush().unwrap();
@@ -69,10 +97,9 @@ impl Player for HumanPlayer {
 
                     let row = row_num - 1;
 
-                    if board.is_valid_move(col, row, _stone) {
-                        return Some((col, row));
-                    } else {
-                        println!("Invalid move! That position is either occupied or would be suicide without capturing.");
+                    match view.check_placement(col, row) {
+                        Ok(()) => return Some((col, row)),
+                        Err(error) => println!("Invalid move! {}", error),
                     }
                 }
                 Err(_) => {
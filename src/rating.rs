@@ -0,0 +1,247 @@
+use crate::stats::MatchStats;
+use crate::tournament::CrossTable;
+use std::f64::consts::LN_10;
+use std::fmt;
+
+// Standard normal quantile of a two-sided 95% confidence interval
+const Z_95: f64 = 1.959964;
+
+// Virtual draws added to every pairing that met, as BayesElo's prior does,
+// so a player who won or lost all its games still gets a finite rating
+const PRIOR_DRAWS: f64 = 2.0;
+
+const MAX_ITERATIONS: usize = 100;
+
+// Elo points per natural-log unit of the odds of winning
+const ELO_PER_NEPER: f64 = 400.0 / LN_10;
+
+// The Elo difference that makes `score` the expected share of the points,
+// infinite for a score of 0 or 1
+pub fn elo_from_score(score: f64) -> f64 {
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+// The share of the points expected against a player rated `elo` lower
+pub fn expected_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+// An Elo difference and the half-width of its 95% confidence interval
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EloEstimate {
+    pub elo: f64,
+    pub margin: f64,
+}
+
+impl fmt::Display for EloEstimate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:+.1} ± {:.1}", self.elo, self.margin)
+    }
+}
+
+// How much stronger the first player of a match played, from its score and
+// the spread of the game results. Both are infinite for a perfect score and
+// NaN for a match without games.
+pub fn match_elo(stats: MatchStats) -> EloEstimate {
+    let games = stats.games() as f64;
+    let score = stats.score();
+    let deviation = (stats.score_variance() / games).sqrt();
+    let lower = elo_from_score((score - Z_95 * deviation).max(0.0));
+    let upper = elo_from_score((score + Z_95 * deviation).min(1.0));
+    EloEstimate {
+        elo: elo_from_score(score),
+        margin: (upper - lower) / 2.0,
+    }
+}
+
+// One entrant's place on the rating list
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rating {
+    pub entrant: usize, // Index in the cross-table
+    pub name: String,
+    pub estimate: EloEstimate,
+    pub games: u32,
+    pub score: f64,
+}
+
+// Maximum likelihood ratings of a tournament's entrants in the
+// Bradley-Terry model, a draw counting as half a win. The anchor is rated 0
+// (RandomAI makes a good one) and the margins are relative to it, taken
+// from the curvature of the likelihood. Entrants with no chain of games
+// to the anchor cannot be rated and are left out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ratings {
+    ratings: Vec<Rating>, // Best first
+}
+
+impl Ratings {
+    pub fn estimate(table: &CrossTable, anchor: usize) -> Self {
+        let rated = connected(table, anchor);
+        let count = rated.len();
+
+        // Games and points between the rated entrants, the prior included
+        let mut games = vec![vec![0.0; count]; count];
+        let mut points = vec![vec![0.0; count]; count];
+        for (i, &a) in rated.iter().enumerate() {
+            for (j, &b) in rated.iter().enumerate() {
+                if let Some(stats) = table.result(a, b).filter(|s| s.games() > 0) {
+                    games[i][j] = stats.games() as f64 + PRIOR_DRAWS;
+                    points[i][j] = stats.wins as f64 + stats.draws as f64 / 2.0 + PRIOR_DRAWS / 2.0;
+                }
+            }
+        }
+
+        // Newton's method on the log-likelihood with the anchor, the first
+        // rated entrant, held at 0. The likelihood is concave, so it
+        // converges from anywhere.
+        let mut strength = vec![0.0_f64; count];
+        let mut covariance = vec![vec![0.0; count - 1]; count - 1];
+        for _ in 0..MAX_ITERATIONS {
+            let mut gradient = vec![0.0; count - 1];
+            let mut information = vec![vec![0.0; count - 1]; count - 1];
+            for i in 0..count {
+                for j in 0..count {
+                    if games[i][j] == 0.0 {
+                        continue;
+                    }
+                    let p = 1.0 / (1.0 + (strength[j] - strength[i]).exp());
+                    let curvature = games[i][j] * p * (1.0 - p);
+                    if i > 0 {
+                        gradient[i - 1] += points[i][j] - games[i][j] * p;
+                        information[i - 1][i - 1] += curvature;
+                        if j > 0 {
+                            information[i - 1][j - 1] -= curvature;
+                        }
+                    }
+                }
+            }
+            covariance = invert(information);
+            let mut largest_step: f64 = 0.0;
+            for i in 1..count {
+                let step: f64 = (0..count - 1)
+                    .map(|j| covariance[i - 1][j] * gradient[j])
+                    .sum();
+                strength[i] += step;
+                largest_step = largest_step.max(step.abs());
+            }
+            if largest_step < 1e-9 {
+                break;
+            }
+        }
+
+        let mut ratings: Vec<Rating> = rated
+            .iter()
+            .enumerate()
+            .map(|(i, &entrant)| {
+                let total = table.total(entrant);
+                let variance = if i == 0 {
+                    0.0
+                } else {
+                    covariance[i - 1][i - 1]
+                };
+                Rating {
+                    entrant,
+                    name: table.names()[entrant].clone(),
+                    estimate: EloEstimate {
+                        elo: strength[i] * ELO_PER_NEPER,
+                        margin: Z_95 * variance.sqrt() * ELO_PER_NEPER,
+                    },
+                    games: total.games(),
+                    score: total.score(),
+                }
+            })
+            .collect();
+        ratings.sort_by(|a, b| b.estimate.elo.total_cmp(&a.estimate.elo));
+        Ratings { ratings }
+    }
+
+    // The rated entrants, best first
+    pub fn ratings(&self) -> &[Rating] {
+        &self.ratings
+    }
+
+    // The rating of the entrant at `entrant` in the cross-table, if rated
+    pub fn rating(&self, entrant: usize) -> Option<&Rating> {
+        self.ratings.iter().find(|r| r.entrant == entrant)
+    }
+}
+
+// Ranks with rating, margin, games and score, best first
+impl fmt::Display for Ratings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name_width = self
+            .ratings
+            .iter()
+            .map(|r| r.name.len())
+            .max()
+            .unwrap_or(0)
+            .max(4);
+        writeln!(
+            f,
+            "{:>4}  {:<name_width$}  {:>7}  {:>6}  {:>5}  {:>6}",
+            "Rank", "Name", "Elo", "±", "Games", "Score"
+        )?;
+        for (rank, rating) in self.ratings.iter().enumerate() {
+            writeln!(
+                f,
+                "{:>4}  {:<name_width$}  {:>7.1}  {:>6.1}  {:>5}  {:>5.1}%",
+                rank + 1,
+                rating.name,
+                rating.estimate.elo,
+                rating.estimate.margin,
+                rating.games,
+                rating.score * 100.0
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// The entrants linked to `anchor` by games, the anchor first
+fn connected(table: &CrossTable, anchor: usize) -> Vec<usize> {
+    let mut found = vec![anchor];
+    let mut next = 0;
+    while next < found.len() {
+        let a = found[next];
+        for b in 0..table.names().len() {
+            let met = table.result(a, b).is_some_and(|s| s.games() > 0);
+            if met && !found.contains(&b) {
+                found.push(b);
+            }
+        }
+        next += 1;
+    }
+    found
+}
+
+// Gauss-Jordan elimination with partial pivoting. The matrices inverted
+// here are positive definite, so a pivot is never zero.
+fn invert(mut matrix: Vec<Vec<f64>>) -> Vec<Vec<f64>> {
+    let size = matrix.len();
+    let mut inverse: Vec<Vec<f64>> = (0..size)
+        .map(|i| (0..size).map(|j| if i == j { 1.0 } else { 0.0 }).collect())
+        .collect();
+    for column in 0..size {
+        let pivot = (column..size)
+            .max_by(|&a, &b| matrix[a][column].abs().total_cmp(&matrix[b][column].abs()))
+            .unwrap();
+        matrix.swap(column, pivot);
+        inverse.swap(column, pivot);
+        let scale = matrix[column][column];
+        for j in 0..size {
+            matrix[column][j] /= scale;
+            inverse[column][j] /= scale;
+        }
+        for row in 0..size {
+            let factor = matrix[row][column];
+            if row == column || factor == 0.0 {
+                continue;
+            }
+            for j in 0..size {
+                matrix[row][j] -= factor * matrix[column][j];
+                inverse[row][j] -= factor * inverse[column][j];
+            }
+        }
+    }
+    inverse
+}
@@ -0,0 +1,88 @@
+use crate::board::Stone;
+use crate::rules::{ScoringRule, SideScore};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Termination {
+    ConsecutivePasses,
+    MoveLimit,
+    // Scored before the game ended, e.g. for GTP final_score
+    Adjudicated,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameResult {
+    pub winner: Option<Stone>, // None is a draw
+    pub margin: f64,
+    pub black: SideScore,
+    pub white: SideScore,
+    pub scoring: ScoringRule,
+    pub termination: Termination,
+    pub move_count: usize, // Stones played, passes excluded
+}
+
+impl GameResult {
+    pub fn new(
+        black: SideScore,
+        white: SideScore,
+        scoring: ScoringRule,
+        termination: Termination,
+        move_count: usize,
+    ) -> Self {
+        let winner = if black.total > white.total {
+            Some(Stone::Black)
+        } else if white.total > black.total {
+            Some(Stone::White)
+        } else {
+            None
+        };
+
+        GameResult {
+            winner,
+            margin: (black.total - white.total).abs(),
+            black,
+            white,
+            scoring,
+            termination,
+            move_count,
+        }
+    }
+
+    pub fn score(&self, stone: Stone) -> &SideScore {
+        match stone {
+            Stone::Black => &self.black,
+            Stone::White => &self.white,
+        }
+    }
+
+    // How a side's total was reached, e.g. "12 stones + 3 captured = 15"
+    pub fn breakdown(&self, stone: Stone) -> String {
+        let score = self.score(stone);
+        let stones = format!("{} stones", score.stones);
+        let territory = format!("{} territory", score.territory);
+        let captured = format!("{} captured", score.captures);
+
+        let mut parts = match self.scoring {
+            ScoringRule::StonesAndCaptures => vec![stones, captured],
+            ScoringRule::Stones => vec![stones],
+            ScoringRule::Area => vec![stones, territory],
+            ScoringRule::Territory => vec![territory, captured],
+        };
+        if score.komi != 0.0 {
+            parts.push(format!("{} komi", score.komi));
+        }
+
+        format!("{} = {}", parts.join(" + "), score.total)
+    }
+}
+
+// The SGF/GTP result notation: "B+3", "W+0.5" or "0" for a draw
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.winner {
+            Some(Stone::Black) => write!(f, "B+{}", self.margin),
+            Some(Stone::White) => write!(f, "W+{}", self.margin),
+            None => write!(f, "0"),
+        }
+    }
+}
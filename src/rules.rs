@@ -0,0 +1,167 @@
+use crate::board::Board;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScoringRule {
+    // Stones on the board plus captured stones (the original jungo scoring)
+    #[default]
+    StonesAndCaptures,
+    // Pure Jungo stone counting: stones on the board only
+    Stones,
+    // Stones on the board plus surrounded empty points
+    Area,
+    // Surrounded empty points plus captured stones
+    Territory,
+}
+
+impl fmt::Display for ScoringRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScoringRule::StonesAndCaptures => write!(f, "stones+captures"),
+            ScoringRule::Stones => write!(f, "stones"),
+            ScoringRule::Area => write!(f, "area"),
+            ScoringRule::Territory => write!(f, "territory"),
+        }
+    }
+}
+
+impl FromStr for ScoringRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "stones+captures" | "default" => Ok(ScoringRule::StonesAndCaptures),
+            "stones" | "jungo" => Ok(ScoringRule::Stones),
+            "area" => Ok(ScoringRule::Area),
+            "territory" => Ok(ScoringRule::Territory),
+            _ => Err(format!("unknown scoring rule '{}'", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KoRule {
+    // A move may not recreate the position before the opponent's last move
+    #[default]
+    Simple,
+    // A move may not recreate any earlier position
+    PositionalSuperko,
+    // A move may not recreate any earlier position with the same side to move
+    SituationalSuperko,
+}
+
+impl KoRule {
+    // Whether a move leading to `new_hash` (and `new_situation`, see
+    // Board::get_situation_hash) repeats a position this rule forbids.
+    // Both histories end with the current position.
+    pub fn is_repetition(
+        &self,
+        board_history: &[u64],
+        situation_history: &[u64],
+        new_hash: u64,
+        new_situation: u64,
+    ) -> bool {
+        match self {
+            KoRule::Simple => {
+                let len = board_history.len();
+                len >= 2 && board_history[len - 2] == new_hash
+            }
+            KoRule::PositionalSuperko => board_history.contains(&new_hash),
+            KoRule::SituationalSuperko => situation_history.contains(&new_situation),
+        }
+    }
+}
+
+impl fmt::Display for KoRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KoRule::Simple => write!(f, "simple"),
+            KoRule::PositionalSuperko => write!(f, "positional"),
+            KoRule::SituationalSuperko => write!(f, "situational"),
+        }
+    }
+}
+
+impl FromStr for KoRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "simple" => Ok(KoRule::Simple),
+            "positional" | "psk" => Ok(KoRule::PositionalSuperko),
+            "situational" | "ssk" => Ok(KoRule::SituationalSuperko),
+            _ => Err(format!("unknown ko rule '{}'", s)),
+        }
+    }
+}
+
+// Points for one side, broken down by where they come from. Every
+// component is filled in, `total` only adds up the ones the rule counts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SideScore {
+    pub stones: usize,
+    pub territory: usize,
+    pub captures: usize,
+    pub komi: f64,
+    pub total: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rules {
+    pub scoring: ScoringRule,
+    pub komi: f64, // Added to White's score
+    pub ko: KoRule,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            scoring: ScoringRule::default(),
+            komi: 0.0,
+            ko: KoRule::default(),
+        }
+    }
+}
+
+impl Rules {
+    pub fn new(scoring: ScoringRule, komi: f64) -> Self {
+        Rules {
+            scoring,
+            komi,
+            ko: KoRule::default(),
+        }
+    }
+
+    // Scores the board as it stands, as (black, white)
+    pub fn score(&self, board: &Board) -> (SideScore, SideScore) {
+        let (black_stones, white_stones) = board.count_stones();
+        let (black_captured, white_captured) = board.get_captured();
+        // Flood filling is only needed when territory counts
+        let (black_territory, white_territory) = match self.scoring {
+            ScoringRule::Area | ScoringRule::Territory => board.count_territory(),
+            _ => (0, 0),
+        };
+
+        let side = |stones: usize, territory: usize, captures: usize, komi: f64| {
+            let points = match self.scoring {
+                ScoringRule::StonesAndCaptures => stones + captures,
+                ScoringRule::Stones => stones,
+                ScoringRule::Area => stones + territory,
+                ScoringRule::Territory => territory + captures,
+            };
+            SideScore {
+                stones,
+                territory,
+                captures,
+                komi,
+                total: points as f64 + komi,
+            }
+        };
+
+        (
+            side(black_stones, black_territory, black_captured, 0.0),
+            side(white_stones, white_territory, white_captured, self.komi),
+        )
+    }
+}
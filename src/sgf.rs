@@ -0,0 +1,545 @@
+use crate::board::Stone;
+use crate::game::{Game, Move, MoveError};
+use chrono::{Local, NaiveDate};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// Smart Game Format (FF[4]) game records
+
+#[derive(Debug, Clone)]
+pub struct SgfRecord {
+    pub board_size: usize,
+    pub black_name: String,
+    pub white_name: String,
+    pub komi: f64,
+    pub result: Option<String>, // e.g. "B+3", "W+0.5" or "0" for a draw
+    pub date: NaiveDate,
+    pub moves: Vec<(Stone, Move)>,
+}
+
+impl SgfRecord {
+    pub fn from_game(game: &Game, black_name: &str, white_name: &str) -> Self {
+        // Only finished games get a result
+        let result = game.result().map(|result| result.to_string());
+
+        SgfRecord {
+            board_size: game.board.size(),
+            black_name: black_name.to_string(),
+            white_name: white_name.to_string(),
+            komi: game.rules.komi,
+            result,
+            date: Local::now().date_naive(),
+            moves: game.moves.clone(),
+        }
+    }
+
+    pub fn to_sgf(&self) -> String {
+        let mut sgf = String::from("(;FF[4]GM[1]CA[UTF-8]");
+        sgf.push_str(&format!("AP[jungo:{}]", env!("CARGO_PKG_VERSION")));
+        sgf.push_str(&format!("SZ[{}]", self.board_size));
+        sgf.push_str(&format!("KM[{}]", self.komi));
+        sgf.push_str(&format!("PB[{}]", escape_text(&self.black_name)));
+        sgf.push_str(&format!("PW[{}]", escape_text(&self.white_name)));
+        sgf.push_str(&format!("DT[{}]", self.date.format("%Y-%m-%d")));
+        if let Some(result) = &self.result {
+            sgf.push_str(&format!("RE[{}]", escape_text(result)));
+        }
+
+        for (i, &(stone, mv)) in self.moves.iter().enumerate() {
+            // Keep lines short for people reading the raw file
+            if i % 10 == 0 {
+                sgf.push('\n');
+            }
+            let color = match stone {
+                Stone::Black => 'B',
+                Stone::White => 'W',
+            };
+            sgf.push_str(&format!(";{}[{}]", color, format_point(mv)));
+        }
+
+        sgf.push_str(")\n");
+        sgf
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_sgf())
+    }
+}
+
+// SGF points are two lowercase letters (column, row) counted from the
+// top-left corner, which matches Board coordinates. Passes are empty.
+fn format_point(mv: Move) -> String {
+    match mv {
+        Move::Place(x, y) => format!("{}{}", (b'a' + x as u8) as char, (b'a' + y as u8) as char),
+        Move::Pass => String::new(),
+    }
+}
+
+fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c == ']' || c == '\\' {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SgfError {
+    Syntax {
+        offset: usize,
+        message: &'static str,
+    },
+    Unsupported(String),
+    InvalidProperty(String),
+    IllegalMove {
+        move_number: usize,
+        stone: Stone,
+        mv: Move,
+        reason: MoveError,
+    },
+}
+
+impl fmt::Display for SgfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SgfError::Syntax { offset, message } => {
+                write!(f, "SGF syntax error at byte {}: {}", offset, message)
+            }
+            SgfError::Unsupported(what) => write!(f, "Unsupported SGF content: {}", what),
+            SgfError::InvalidProperty(what) => write!(f, "Invalid SGF property: {}", what),
+            SgfError::IllegalMove {
+                move_number,
+                stone,
+                mv,
+                reason,
+            } => write!(
+                f,
+                "Illegal move {} by {:?} at [{}]: {}",
+                move_number,
+                stone,
+                format_point(*mv),
+                reason
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SgfError {}
+
+// One node of an SGF game tree. A sequence of nodes is stored as a chain
+// where every node has a single child; variations are additional children.
+#[derive(Debug, Clone, Default)]
+pub struct SgfNode {
+    pub properties: Vec<(String, Vec<String>)>,
+    pub children: Vec<SgfNode>,
+}
+
+impl SgfNode {
+    pub fn get(&self, id: &str) -> Option<&str> {
+        self.get_all(id).first().map(|v| v.as_str())
+    }
+
+    pub fn get_all(&self, id: &str) -> &[String] {
+        self.properties
+            .iter()
+            .find(|(prop, _)| prop == id)
+            .map(|(_, values)| values.as_slice())
+            .unwrap_or(&[])
+    }
+}
+
+// Parses the first game tree of an SGF collection and returns its root node
+pub fn parse(text: &str) -> Result<SgfNode, SgfError> {
+    let mut parser = Parser {
+        bytes: text.as_bytes(),
+        pos: 0,
+    };
+    parser.skip_whitespace();
+    parser.game_tree()
+}
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &'static str) -> SgfError {
+        SgfError::Syntax {
+            offset: self.pos,
+            message,
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.pos < self.bytes.len() && self.bytes[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8, message: &'static str) -> Result<(), SgfError> {
+        self.skip_whitespace();
+        if self.bytes.get(self.pos) != Some(&byte) {
+            return Err(self.error(message));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn game_tree(&mut self) -> Result<SgfNode, SgfError> {
+        self.expect(b'(', "expected '('")?;
+
+        // Parse the node sequence, then the variations hanging off its end
+        let mut sequence = Vec::new();
+        while self.peek() == Some(b';') {
+            self.pos += 1;
+            sequence.push(self.node()?);
+        }
+        if sequence.is_empty() {
+            return Err(self.error("expected ';'"));
+        }
+
+        let mut variations = Vec::new();
+        while self.peek() == Some(b'(') {
+            variations.push(self.game_tree()?);
+        }
+        self.expect(b')', "expected ')'")?;
+
+        let mut tail = sequence.pop().unwrap();
+        tail.children = variations;
+        while let Some(mut node) = sequence.pop() {
+            node.children = vec![tail];
+            tail = node;
+        }
+        Ok(tail)
+    }
+
+    fn node(&mut self) -> Result<SgfNode, SgfError> {
+        let mut node = SgfNode::default();
+
+        while let Some(c) = self.peek() {
+            if !c.is_ascii_uppercase() {
+                break;
+            }
+            let start = self.pos;
+            while self.pos < self.bytes.len() && self.bytes[self.pos].is_ascii_alphabetic() {
+                self.pos += 1;
+            }
+            // FF[3] allowed lowercase letters in identifiers; they carry no meaning
+            let id: String = self.bytes[start..self.pos]
+                .iter()
+                .filter(|b| b.is_ascii_uppercase())
+                .map(|&b| b as char)
+                .collect();
+
+            let mut values = Vec::new();
+            while self.peek() == Some(b'[') {
+                self.pos += 1;
+                values.push(self.value()?);
+            }
+            if values.is_empty() {
+                return Err(self.error("property without value"));
+            }
+            node.properties.push((id, values));
+        }
+
+        Ok(node)
+    }
+
+    fn value(&mut self) -> Result<String, SgfError> {
+        let mut value = Vec::new();
+        loop {
+            match self.bytes.get(self.pos) {
+                None => return Err(self.error("unterminated property value")),
+                Some(b']') => {
+                    self.pos += 1;
+                    break;
+                }
+                Some(b'\\') => {
+                    self.pos += 1;
+                    match self.bytes.get(self.pos) {
+                        // Escaped line breaks are soft breaks and disappear
+                        Some(b'\n') | Some(b'\r') => {}
+                        Some(&b) => value.push(b),
+                        None => return Err(self.error("unterminated property value")),
+                    }
+                    self.pos += 1;
+                }
+                Some(&b) => {
+                    value.push(b);
+                    self.pos += 1;
+                }
+            }
+        }
+        Ok(String::from_utf8_lossy(&value).into_owned())
+    }
+}
+
+// Setup stones and/or a move, as recorded in one SGF node
+#[derive(Debug, Clone, Default)]
+struct ReplayStep {
+    setup: Vec<((usize, usize), Option<Stone>)>,
+    player_to_move: Option<Stone>,
+    mv: Option<(Stone, Move)>,
+}
+
+// Steps through one line of an SGF game tree, keeping a Game for every
+// position so AIs can be started from any point of the record.
+pub struct Replay {
+    root: SgfNode,
+    steps: Vec<ReplayStep>,
+    position: usize,
+    game: Game,
+    previous: Vec<Game>,
+}
+
+impl Replay {
+    // Loads the main line (first variation at every branch)
+    pub fn from_sgf(text: &str) -> Result<Self, SgfError> {
+        Self::from_tree(parse(text)?, &[])
+    }
+
+    // Loads the line picking `variation[i]` at the i-th branching point;
+    // branches past the end of `variation` follow the main line
+    pub fn from_tree(root: SgfNode, variation: &[usize]) -> Result<Self, SgfError> {
+        if let Some(gm) = root.get("GM") {
+            if gm.trim() != "1" {
+                return Err(SgfError::Unsupported(format!("game type GM[{}]", gm)));
+            }
+        }
+
+        let size = match root.get("SZ") {
+            Some(sz) => parse_size(sz)?,
+            None => 19,
+        };
+
+        let mut game = Game::new(size);
+        if let Some(km) = root.get("KM") {
+            game.rules.komi = km
+                .trim()
+                .parse()
+                .map_err(|_| SgfError::InvalidProperty(format!("KM[{}]", km)))?;
+        }
+        let root_step = read_step(&root, size)?;
+        apply_setup(&mut game, &root_step);
+        if root_step.mv.is_some() {
+            return Err(SgfError::Unsupported("move in root node".to_string()));
+        }
+
+        let mut steps = Vec::new();
+        let mut node = &root;
+        let mut branch = 0;
+        while !node.children.is_empty() {
+            let mut choice = 0;
+            if node.children.len() > 1 {
+                choice = variation.get(branch).copied().unwrap_or(0);
+                branch += 1;
+                if choice >= node.children.len() {
+                    return Err(SgfError::InvalidProperty(format!(
+                        "variation {} does not exist",
+                        choice
+                    )));
+                }
+            }
+            node = &node.children[choice];
+            steps.push(read_step(node, size)?);
+        }
+
+        Ok(Replay {
+            root,
+            steps,
+            position: 0,
+            game,
+            previous: Vec::new(),
+        })
+    }
+
+    // Root node of the record, for header properties such as PB, PW, KM or RE
+    pub fn root(&self) -> &SgfNode {
+        &self.root
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn into_game(self) -> Game {
+        self.game
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    // Plays the next node. Returns Ok(false) at the end of the line.
+    pub fn forward(&mut self) -> Result<bool, SgfError> {
+        let Some(step) = self.steps.get(self.position) else {
+            return Ok(false);
+        };
+
+        let mut game = self.game.clone();
+        apply_setup(&mut game, step);
+
+        if let Some((stone, mv)) = step.mv {
+            game.current_turn = stone;
+            if game.is_over() {
+                game.resume();
+            }
+            game.apply_move(mv)
+                .map_err(|reason| SgfError::IllegalMove {
+                    move_number: game.moves.len() + 1,
+                    stone,
+                    mv,
+                    reason,
+                })?;
+            if let Some(next) = step.player_to_move {
+                game.current_turn = next;
+            }
+        }
+
+        self.previous.push(std::mem::replace(&mut self.game, game));
+        self.position += 1;
+        Ok(true)
+    }
+
+    // Steps back one node. Returns false at the start of the line.
+    pub fn backward(&mut self) -> bool {
+        match self.previous.pop() {
+            Some(game) => {
+                self.game = game;
+                self.position -= 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn seek(&mut self, position: usize) -> Result<(), SgfError> {
+        while self.position > position && self.backward() {}
+        while self.position < position && self.forward()? {}
+        Ok(())
+    }
+}
+
+fn parse_size(sz: &str) -> Result<usize, SgfError> {
+    let mut parts = sz.split(':').map(|p| p.trim().parse::<usize>());
+    let size = match (parts.next(), parts.next()) {
+        (Some(Ok(n)), None) => n,
+        (Some(Ok(w)), Some(Ok(h))) if w == h => w,
+        _ => return Err(SgfError::Unsupported(format!("board size SZ[{}]", sz))),
+    };
+    if !(2..=25).contains(&size) {
+        return Err(SgfError::Unsupported(format!("board size SZ[{}]", sz)));
+    }
+    Ok(size)
+}
+
+fn read_step(node: &SgfNode, size: usize) -> Result<ReplayStep, SgfError> {
+    let mut step = ReplayStep::default();
+
+    for (id, stone) in [
+        ("AE", None),
+        ("AB", Some(Stone::Black)),
+        ("AW", Some(Stone::White)),
+    ] {
+        for value in node.get_all(id) {
+            for point in parse_point_list(value, size)? {
+                step.setup.push((point, stone));
+            }
+        }
+    }
+
+    step.player_to_move = match node.get("PL") {
+        Some("B") | Some("b") => Some(Stone::Black),
+        Some("W") | Some("w") => Some(Stone::White),
+        Some(other) => return Err(SgfError::InvalidProperty(format!("PL[{}]", other))),
+        None => None,
+    };
+
+    for (id, stone) in [("B", Stone::Black), ("W", Stone::White)] {
+        if let Some(value) = node.get(id) {
+            if step.mv.is_some() {
+                return Err(SgfError::InvalidProperty("B and W in one node".to_string()));
+            }
+            step.mv = Some((stone, parse_move(value, size)?));
+        }
+    }
+
+    Ok(step)
+}
+
+fn apply_setup(game: &mut Game, step: &ReplayStep) {
+    if step.setup.is_empty() && step.player_to_move.is_none() {
+        return;
+    }
+    for &((x, y), stone) in &step.setup {
+        game.board.set_stone(x, y, stone);
+    }
+    if !step.setup.is_empty() {
+        game.board_history.push(game.board.get_hash());
+    }
+    if let Some(stone) = step.player_to_move {
+        game.current_turn = stone;
+    }
+    if !step.setup.is_empty() {
+        game.situation_history
+            .push(game.board.get_situation_hash(game.current_turn));
+    }
+}
+
+fn parse_point(value: &str, size: usize) -> Result<(usize, usize), SgfError> {
+    let bytes = value.trim().as_bytes();
+    if bytes.len() != 2 || !bytes[0].is_ascii_lowercase() || !bytes[1].is_ascii_lowercase() {
+        return Err(SgfError::InvalidProperty(format!("point [{}]", value)));
+    }
+    let (x, y) = ((bytes[0] - b'a') as usize, (bytes[1] - b'a') as usize);
+    if x >= size || y >= size {
+        return Err(SgfError::InvalidProperty(format!("point [{}]", value)));
+    }
+    Ok((x, y))
+}
+
+// Point lists may be compressed into rectangles such as "aa:cc"
+fn parse_point_list(value: &str, size: usize) -> Result<Vec<(usize, usize)>, SgfError> {
+    match value.split_once(':') {
+        Some((from, to)) => {
+            let (x1, y1) = parse_point(from, size)?;
+            let (x2, y2) = parse_point(to, size)?;
+            let mut points = Vec::new();
+            for y in y1.min(y2)..=y1.max(y2) {
+                for x in x1.min(x2)..=x1.max(x2) {
+                    points.push((x, y));
+                }
+            }
+            Ok(points)
+        }
+        None => Ok(vec![parse_point(value, size)?]),
+    }
+}
+
+// "tt" is the FF[3] pass for boards up to 19x19
+fn parse_move(value: &str, size: usize) -> Result<Move, SgfError> {
+    let value = value.trim();
+    if value.is_empty() || (value == "tt" && size <= 19) {
+        return Ok(Move::Pass);
+    }
+    parse_point(value, size).map(|(x, y)| Move::Place(x, y))
+}
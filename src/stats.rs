@@ -1,17 +1,28 @@
 use crate::ai::RandomAI;
 use crate::board::Stone;
 use crate::game::Game;
+use crate::observer::SilentObserver;
 use crate::player::Player;
-use std::time::Instant;
+use crate::rating::{expected_score, match_elo};
+use crate::result::GameResult;
+use crate::rules::Rules;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::ops::{Add, AddAssign};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
 
 pub struct GameStats {
     pub black_wins: u32,
     pub white_wins: u32,
     pub draws: u32,
-    pub total_black_score: i32,
-    pub total_white_score: i32,
+    pub total_black_score: f64,
+    pub total_white_score: f64,
     pub total_moves: u32,
-    pub total_duration: std::time::Duration,
+    pub total_duration: std::time::Duration, // Sum of the game durations
+    pub elapsed: std::time::Duration,        // Wall-clock time of the whole run
 }
 
 impl Default for GameStats {
@@ -20,10 +31,11 @@ impl Default for GameStats {
             black_wins: 0,
             white_wins: 0,
             draws: 0,
-            total_black_score: 0,
-            total_white_score: 0,
+            total_black_score: 0.0,
+            total_white_score: 0.0,
             total_moves: 0,
             total_duration: std::time::Duration::new(0, 0),
+            elapsed: std::time::Duration::new(0, 0),
         }
     }
 }
@@ -33,6 +45,19 @@ impl GameStats {
         Self::default()
     }
 
+    pub fn record(&mut self, result: &GameResult, duration: std::time::Duration) {
+        self.total_black_score += result.black.total;
+        self.total_white_score += result.white.total;
+        self.total_moves += result.move_count as u32;
+        self.total_duration += duration;
+
+        match result.winner {
+            Some(Stone::Black) => self.black_wins += 1,
+            Some(Stone::White) => self.white_wins += 1,
+            None => self.draws += 1,
+        }
+    }
+
     pub fn print_summary(&self, total_games: u32, board_size: usize) {
         println!(
             "\n=== Game Statistics for {}x{} Board ===",
@@ -55,19 +80,25 @@ impl GameStats {
             self.draws,
             (self.draws as f64 / total_games as f64) * 100.0
         );
+        let black = MatchStats {
+            wins: self.black_wins,
+            losses: self.white_wins,
+            draws: self.draws,
+        };
+        println!("Black advantage: {} Elo", match_elo(black));
 
         println!("\nScore Statistics:");
         println!(
             "Average Black score: {:.2}",
-            self.total_black_score as f64 / total_games as f64
+            self.total_black_score / total_games as f64
         );
         println!(
             "Average White score: {:.2}",
-            self.total_white_score as f64 / total_games as f64
+            self.total_white_score / total_games as f64
         );
         println!(
             "Average score difference: {:.2}",
-            (self.total_black_score - self.total_white_score) as f64 / total_games as f64
+            (self.total_black_score - self.total_white_score) / total_games as f64
         );
 
         println!("\nGame Statistics:");
@@ -79,110 +110,394 @@ impl GameStats {
             "Average game duration: {:.2}ms",
             self.total_duration.as_millis() as f64 / total_games as f64
         );
-        println!("Total time: {:.2}s", self.total_duration.as_secs_f64());
+        println!("Total time: {:.2}s", self.elapsed.as_secs_f64());
     }
 }
 
-pub fn run_game_silent(board_size: usize) -> (i32, i32, u32) {
-    let mut game = Game::new(board_size);
-    let player1 = RandomAI::new();
-    let player2 = RandomAI::new();
-    let mut move_count = 0;
+// One game as the statistics and match runners played it
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameRecord {
+    pub game: u32, // Numbered from 0 in the order of the run
+    pub board_size: usize,
+    pub black: String,
+    pub white: String,
+    pub result: GameResult,
+    pub duration: Duration,
+    pub seed: Option<u64>, // What both players' moves follow from, if seeded
+}
 
-    loop {
-        let current_player: &dyn Player = match game.current_turn {
-            Stone::Black => &player1,
-            Stone::White => &player2,
-        };
+pub fn run_game_silent(board_size: usize, rules: Rules) -> GameResult {
+    let mut game = Game::with_rules(board_size, rules);
+    let mut player1 = RandomAI::new();
+    let mut player2 = RandomAI::new();
 
-        match current_player.get_move(&game.board, game.current_turn) {
-            Some((x, y)) => {
-                // Check if the move is valid
-                if !game.board.is_valid_move(x, y, game.current_turn) {
-                    continue;
-                }
+    game.play_with(&mut player1, &mut player2, &mut SilentObserver);
+    game.result().unwrap()
+}
 
-                // Clone board to test the move
-                let mut test_board = game.board.clone();
-                if test_board.place_stone(x, y, game.current_turn).is_ok() {
-                    let new_hash = test_board.get_hash();
-
-                    // Check Ko rule: see if this board state occurred 2 moves ago
-                    let history_len = game.board_history.len();
-                    if history_len >= 2 && game.board_history[history_len - 2] == new_hash {
-                        continue; // Ko rule violation
-                    }
-
-                    // Move is valid, apply it
-                    let board_before_move = game.board.clone();
-                    if game.board.place_stone(x, y, game.current_turn).is_ok() {
-                        game.consecutive_passes = 0;
-                        game.previous_board = Some(board_before_move);
-                        game.board_history.push(game.board.get_hash());
-                        move_count += 1;
-                    }
+// A game between two RandomAIs whose moves all follow from `seed`
+pub fn run_game_seeded(board_size: usize, rules: Rules, seed: u64) -> GameResult {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut game = Game::with_rules(board_size, rules);
+    let mut player1 = RandomAI::new().with_seed(rng.gen());
+    let mut player2 = RandomAI::new().with_seed(rng.gen());
+
+    game.play_with(&mut player1, &mut player2, &mut SilentObserver);
+    game.result().unwrap()
+}
+
+// Plays random games on every available core
+pub fn run_statistics(board_size: usize, num_games: u32, rules: Rules) -> GameStats {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    run_statistics_with(
+        board_size,
+        num_games,
+        rules,
+        threads,
+        rand::random(),
+        &mut |_| {},
+    )
+}
+
+// Plays `num_games` random games on `threads` threads and sums them up,
+// calling `progress` as for `play_random_games`. The results only depend
+// on the seed. Nothing is printed, so the caller can report as it likes
+// and stdout stays free for exported records.
+pub fn run_statistics_with(
+    board_size: usize,
+    num_games: u32,
+    rules: Rules,
+    threads: usize,
+    seed: u64,
+    progress: &mut dyn FnMut(&GameRecord),
+) -> GameStats {
+    let started = Instant::now();
+    let records = play_random_games(board_size, num_games, rules, threads, seed, progress);
+    let mut stats = GameStats::new();
+    for record in &records {
+        stats.record(&record.result, record.duration);
+    }
+    stats.elapsed = started.elapsed();
+    stats
+}
+
+// Plays `num_games` games between RandomAIs on `threads` threads, game `i`
+// seeded with `seed + i`, calling `progress` on the calling thread with
+// every game as it finishes. The records come back in game order whichever
+// thread played them, so the same seed always gives the same games.
+pub fn play_random_games(
+    board_size: usize,
+    num_games: u32,
+    rules: Rules,
+    threads: usize,
+    seed: u64,
+    progress: &mut dyn FnMut(&GameRecord),
+) -> Vec<GameRecord> {
+    let name = RandomAI::new().name().to_string();
+    // Threads take the next game to play until there are none left
+    let next_game = AtomicU32::new(0);
+    let (sender, receiver) = mpsc::channel();
+    let mut records: Vec<GameRecord> = thread::scope(|scope| {
+        for _ in 0..threads.max(1) {
+            let sender = sender.clone();
+            let (name, next_game) = (&name, &next_game);
+            scope.spawn(move || loop {
+                let game = next_game.fetch_add(1, Ordering::Relaxed);
+                if game >= num_games {
+                    break;
                 }
-            }
-            None => {
-                game.consecutive_passes += 1;
-                if game.consecutive_passes >= 2 {
+                let game_seed = seed.wrapping_add(game as u64);
+                let game_start = Instant::now();
+                let result = run_game_seeded(board_size, rules, game_seed);
+                let record = GameRecord {
+                    game,
+                    board_size,
+                    black: name.clone(),
+                    white: name.clone(),
+                    result,
+                    duration: game_start.elapsed(),
+                    seed: Some(game_seed),
+                };
+                if sender.send(record).is_err() {
                     break;
                 }
-            }
+            });
         }
+        // The channel closes once every worker has dropped its sender
+        drop(sender);
+        receiver.iter().inspect(|record| progress(record)).collect()
+    });
+    records.sort_by_key(|record| record.game);
+    records
+}
 
-        game.current_turn = game.current_turn.opposite();
+// Outcome of a match, from the side of the first player
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MatchStats {
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+}
+
+impl MatchStats {
+    pub fn games(&self) -> u32 {
+        self.wins + self.losses + self.draws
     }
 
-    // Calculate final scores
-    let (black_stones, white_stones) = game.board.count_stones();
-    let (black_captured, white_captured) = game.board.get_captured();
+    // Share of the points won, a draw counting as half a win
+    pub fn score(&self) -> f64 {
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.games() as f64
+    }
 
-    let black_score = (black_stones + black_captured) as i32;
-    let white_score = (white_stones + white_captured) as i32;
+    // Variance of the points of a single game about the score
+    pub fn score_variance(&self) -> f64 {
+        let score = self.score();
+        (self.wins as f64 * (1.0 - score).powi(2)
+            + self.losses as f64 * score.powi(2)
+            + self.draws as f64 * (0.5 - score).powi(2))
+            / self.games() as f64
+    }
 
-    (black_score, white_score, move_count)
+    // The same match from the other player's side
+    pub fn reversed(&self) -> Self {
+        MatchStats {
+            wins: self.losses,
+            losses: self.wins,
+            draws: self.draws,
+        }
+    }
 }
 
-pub fn run_statistics(board_size: usize, num_games: u32) -> GameStats {
-    let mut stats = GameStats::new();
-    let _start_time = Instant::now();
+impl Add for MatchStats {
+    type Output = MatchStats;
+
+    fn add(self, other: MatchStats) -> MatchStats {
+        MatchStats {
+            wins: self.wins + other.wins,
+            losses: self.losses + other.losses,
+            draws: self.draws + other.draws,
+        }
+    }
+}
 
-    println!(
-        "Running {} games on {}x{} board...",
-        num_games, board_size, board_size
-    );
+impl AddAssign for MatchStats {
+    fn add_assign(&mut self, other: MatchStats) {
+        *self = *self + other;
+    }
+}
 
+// Plays `num_games` games between `player` and `opponent`, who swap colors
+// after every game with `player` taking Black first. Games stop after
+// `max_moves` moves, if given, so engines that never pass still finish.
+pub fn run_match(
+    player: &mut dyn Player,
+    opponent: &mut dyn Player,
+    board_size: usize,
+    num_games: u32,
+    rules: Rules,
+    max_moves: Option<usize>,
+) -> MatchStats {
+    run_match_recorded(player, opponent, board_size, num_games, rules, max_moves).0
+}
+
+// `run_match`, also returning the record of every game
+pub fn run_match_recorded(
+    player: &mut dyn Player,
+    opponent: &mut dyn Player,
+    board_size: usize,
+    num_games: u32,
+    rules: Rules,
+    max_moves: Option<usize>,
+) -> (MatchStats, Vec<GameRecord>) {
+    let mut stats = MatchStats::default();
+    let mut records = Vec::new();
     for i in 0..num_games {
-        if i % 1000 == 0 && i > 0 {
-            print!(
-                "Progress: {}/{} games ({:.1}%)...\r",
-                i,
-                num_games,
-                (i as f64 / num_games as f64) * 100.0
-            );
-            use std::io::{self, Write};
-            io::stdout().flush().unwrap();
+        let (outcome, record) = play_match_game(player, opponent, board_size, i, rules, max_moves);
+        stats += outcome;
+        records.push(record);
+    }
+    (stats, records)
+}
+
+// Game `index` of a match, `player` taking Black in the even ones
+fn play_match_game(
+    player: &mut dyn Player,
+    opponent: &mut dyn Player,
+    board_size: usize,
+    index: u32,
+    rules: Rules,
+    max_moves: Option<usize>,
+) -> (MatchStats, GameRecord) {
+    let mut game = Game::with_rules(board_size, rules);
+    game.max_moves = max_moves;
+    let color = if index.is_multiple_of(2) {
+        Stone::Black
+    } else {
+        Stone::White
+    };
+    let game_start = Instant::now();
+    let (black, white) = match color {
+        Stone::Black => {
+            game.play_with(player, opponent, &mut SilentObserver);
+            (player.name(), opponent.name())
         }
+        Stone::White => {
+            game.play_with(opponent, player, &mut SilentObserver);
+            (opponent.name(), player.name())
+        }
+    };
+    let record = GameRecord {
+        game: index,
+        board_size,
+        black: black.to_string(),
+        white: white.to_string(),
+        result: game.result().unwrap(),
+        duration: game_start.elapsed(),
+        seed: None,
+    };
 
-        let game_start = Instant::now();
-        let (black_score, white_score, moves) = run_game_silent(board_size);
-        let game_duration = game_start.elapsed();
+    let mut stats = MatchStats::default();
+    match record.result.winner {
+        Some(winner) if winner == color => stats.wins += 1,
+        Some(_) => stats.losses += 1,
+        None => stats.draws += 1,
+    }
+    (stats, record)
+}
+
+// A sequential probability ratio test of whether a player is `elo1`
+// stronger than its opponent (H1) rather than only `elo0` (H0), accepting
+// H1 by mistake with probability `alpha` and H0 with probability `beta`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+    pub max_games: Option<u32>, // Gives up undecided after this many
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SprtDecision {
+    AcceptH0, // Not the gain hoped for, or worse
+    AcceptH1, // At least the gain hoped for
+}
 
-        stats.total_black_score += black_score;
-        stats.total_white_score += white_score;
-        stats.total_moves += moves;
-        stats.total_duration += game_duration;
+impl Sprt {
+    // Fails unless `elo0` is below `elo1`, as the test could otherwise
+    // never reach a decision
+    pub fn new(elo0: f64, elo1: f64) -> Result<Self, String> {
+        if !elo0.is_finite() || !elo1.is_finite() || elo0 >= elo1 {
+            return Err(format!(
+                "SPRT bounds must be finite with elo0 below elo1, got {} and {}",
+                elo0, elo1
+            ));
+        }
+        Ok(Sprt {
+            elo0,
+            elo1,
+            alpha: 0.05,
+            beta: 0.05,
+            max_games: None,
+        })
+    }
 
-        if black_score > white_score {
-            stats.black_wins += 1;
-        } else if white_score > black_score {
-            stats.white_wins += 1;
+    // Fails unless both error probabilities are strictly between 0 and 1
+    pub fn with_errors(mut self, alpha: f64, beta: f64) -> Result<Self, String> {
+        let valid = |p: f64| p > 0.0 && p < 1.0;
+        if !valid(alpha) || !valid(beta) {
+            return Err(format!(
+                "SPRT error probabilities must lie between 0 and 1, got {} and {}",
+                alpha, beta
+            ));
+        }
+        self.alpha = alpha;
+        self.beta = beta;
+        Ok(self)
+    }
+
+    pub fn with_max_games(mut self, games: u32) -> Self {
+        self.max_games = Some(games);
+        self
+    }
+
+    // The log-likelihood ratios at which H0 and H1 are accepted
+    pub fn bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1.0 - self.alpha)).ln(),
+            ((1.0 - self.beta) / self.alpha).ln(),
+        )
+    }
+
+    // Log-likelihood ratio of H1 against H0 given the match so far, in the
+    // normal approximation to the trinomial model cutechess uses. An extra
+    // win and loss keep it defined while all the results are the same.
+    pub fn llr(&self, stats: &MatchStats) -> f64 {
+        if stats.games() == 0 {
+            return 0.0;
+        }
+        let stats = *stats
+            + MatchStats {
+                wins: 1,
+                losses: 1,
+                draws: 0,
+            };
+        let games = stats.games() as f64;
+        let (score, variance) = (stats.score(), stats.score_variance());
+        let (score0, score1) = (expected_score(self.elo0), expected_score(self.elo1));
+        (score1 - score0) * (2.0 * score - score0 - score1) / (2.0 * variance / games)
+    }
+
+    pub fn decision(&self, stats: &MatchStats) -> Option<SprtDecision> {
+        let (lower, upper) = self.bounds();
+        let llr = self.llr(stats);
+        if llr >= upper {
+            Some(SprtDecision::AcceptH1)
+        } else if llr <= lower {
+            Some(SprtDecision::AcceptH0)
         } else {
-            stats.draws += 1;
+            None
         }
     }
+}
 
-    println!("\nCompleted {} games!", num_games);
-    stats
+// Where a match run as an SPRT ended, the decision None if it ran out of games
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SprtOutcome {
+    pub stats: MatchStats,
+    pub llr: f64,
+    pub decision: Option<SprtDecision>,
+}
+
+// Plays games as `run_match` does until `sprt` reaches a decision, calling
+// `progress` with the game, the results so far and the log-likelihood ratio
+// after every game. Decisions are only taken after both players had each
+// color equally often, so the first move advantage cannot tip them.
+pub fn run_sprt(
+    player: &mut dyn Player,
+    opponent: &mut dyn Player,
+    board_size: usize,
+    sprt: Sprt,
+    rules: Rules,
+    max_moves: Option<usize>,
+    progress: &mut dyn FnMut(&GameRecord, &MatchStats, f64),
+) -> SprtOutcome {
+    let mut stats = MatchStats::default();
+    let mut decision = None;
+    let mut index = 0;
+    while decision.is_none() && sprt.max_games.is_none_or(|max| index < max) {
+        let (outcome, record) =
+            play_match_game(player, opponent, board_size, index, rules, max_moves);
+        stats += outcome;
+        progress(&record, &stats, sprt.llr(&stats));
+        index += 1;
+        if index.is_multiple_of(2) {
+            decision = sprt.decision(&stats);
+        }
+    }
+    SprtOutcome {
+        stats,
+        llr: sprt.llr(&stats),
+        decision,
+    }
 }
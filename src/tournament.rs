@@ -0,0 +1,254 @@
+use crate::player::Player;
+use crate::rules::Rules;
+use crate::stats::{run_match_recorded, GameRecord, MatchStats};
+use std::fmt;
+
+// Builds the player an entrant sends to each of its matches
+pub type PlayerFactory = Box<dyn Fn() -> Box<dyn Player>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    // Every entrant plays every other one
+    #[default]
+    RoundRobin,
+    // The first entrant plays each of the others, who do not meet
+    Gauntlet,
+}
+
+struct Entrant {
+    name: String,
+    factory: PlayerFactory,
+}
+
+// Matches between players, every pairing playing the same number of games
+// on each board size with colors alternating from game to game
+pub struct Tournament {
+    format: Format,
+    entrants: Vec<Entrant>,
+    games: u32,
+    board_sizes: Vec<usize>,
+    rules: Rules,
+    max_moves: Option<usize>,
+}
+
+impl Tournament {
+    pub fn new(format: Format) -> Self {
+        Tournament {
+            format,
+            entrants: Vec::new(),
+            games: 2,
+            board_sizes: vec![9],
+            rules: Rules::default(),
+            max_moves: None,
+        }
+    }
+
+    pub fn with_entrant(
+        mut self,
+        name: impl Into<String>,
+        factory: impl Fn() -> Box<dyn Player> + 'static,
+    ) -> Self {
+        self.entrants.push(Entrant {
+            name: name.into(),
+            factory: Box::new(factory),
+        });
+        self
+    }
+
+    // Games per pairing and board size
+    pub fn with_games(mut self, games: u32) -> Self {
+        self.games = games;
+        self
+    }
+
+    pub fn with_board_sizes(mut self, board_sizes: &[usize]) -> Self {
+        self.board_sizes = board_sizes.to_vec();
+        self
+    }
+
+    pub fn with_rules(mut self, rules: Rules) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    // Ends games after `moves` moves, so engines that never pass finish
+    pub fn with_max_moves(mut self, moves: usize) -> Self {
+        self.max_moves = Some(moves);
+        self
+    }
+
+    // The entrants meeting each other, as indexes in the order they joined
+    pub fn pairings(&self) -> Vec<(usize, usize)> {
+        let count = self.entrants.len();
+        match self.format {
+            Format::RoundRobin => (0..count)
+                .flat_map(|a| (a + 1..count).map(move |b| (a, b)))
+                .collect(),
+            Format::Gauntlet => (1..count).map(|b| (0, b)).collect(),
+        }
+    }
+
+    // Plays every pairing on every board size
+    pub fn run(&self) -> CrossTable {
+        self.run_recorded().0
+    }
+
+    // `run`, also returning the record of every game, numbered in the order
+    // they were played
+    pub fn run_recorded(&self) -> (CrossTable, Vec<GameRecord>) {
+        let mut table = CrossTable::new(self.names());
+        let mut records = Vec::new();
+        for &board_size in &self.board_sizes {
+            let (size_table, size_records) = self.run_board_size_recorded(board_size);
+            table.merge(&size_table);
+            records.extend(size_records);
+        }
+        for (game, record) in records.iter_mut().enumerate() {
+            record.game = game as u32;
+        }
+        (table, records)
+    }
+
+    // Plays every pairing on one board size only
+    pub fn run_board_size(&self, board_size: usize) -> CrossTable {
+        self.run_board_size_recorded(board_size).0
+    }
+
+    // `run_board_size`, also returning the record of every game. Records
+    // name the players by their entrant names.
+    pub fn run_board_size_recorded(&self, board_size: usize) -> (CrossTable, Vec<GameRecord>) {
+        let mut table = CrossTable::new(self.names());
+        let mut records = Vec::new();
+        for (a, b) in self.pairings() {
+            // Each side keeps its player for the whole match
+            let mut player = (self.entrants[a].factory)();
+            let mut opponent = (self.entrants[b].factory)();
+            let (stats, match_records) = run_match_recorded(
+                player.as_mut(),
+                opponent.as_mut(),
+                board_size,
+                self.games,
+                self.rules,
+                self.max_moves,
+            );
+            table.record(a, b, stats);
+
+            // Entrant `a` takes Black in the even games of a match
+            let (name_a, name_b) = (&self.entrants[a].name, &self.entrants[b].name);
+            records.extend(match_records.into_iter().map(|mut record| {
+                let (black, white) = if record.game.is_multiple_of(2) {
+                    (name_a, name_b)
+                } else {
+                    (name_b, name_a)
+                };
+                record.black = black.clone();
+                record.white = white.clone();
+                record
+            }));
+        }
+        for (game, record) in records.iter_mut().enumerate() {
+            record.game = game as u32;
+        }
+        (table, records)
+    }
+
+    fn names(&self) -> Vec<String> {
+        self.entrants.iter().map(|e| e.name.clone()).collect()
+    }
+}
+
+// Wins, losses and draws of every entrant against every other one
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrossTable {
+    names: Vec<String>,
+    results: Vec<Vec<Option<MatchStats>>>, // [a][b] from a's side, None if they did not meet
+}
+
+impl CrossTable {
+    pub fn new(names: Vec<String>) -> Self {
+        let count = names.len();
+        CrossTable {
+            names,
+            results: vec![vec![None; count]; count],
+        }
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    // How entrant `a` did against entrant `b`
+    pub fn result(&self, a: usize, b: usize) -> Option<MatchStats> {
+        self.results[a][b]
+    }
+
+    // Everything entrant `a` played, all opponents together
+    pub fn total(&self, a: usize) -> MatchStats {
+        self.results[a]
+            .iter()
+            .flatten()
+            .fold(MatchStats::default(), |total, stats| total + *stats)
+    }
+
+    // Adds a match between `a` and `b`, given from `a`'s side
+    pub fn record(&mut self, a: usize, b: usize, stats: MatchStats) {
+        *self.results[a][b].get_or_insert_with(MatchStats::default) += stats;
+        *self.results[b][a].get_or_insert_with(MatchStats::default) += stats.reversed();
+    }
+
+    // Adds the results of another table with the same entrants
+    pub fn merge(&mut self, other: &CrossTable) {
+        assert_eq!(
+            self.names, other.names,
+            "cross-tables of different entrants"
+        );
+        for (a, row) in other.results.iter().enumerate() {
+            for (b, stats) in row.iter().enumerate() {
+                if let Some(stats) = stats {
+                    *self.results[a][b].get_or_insert_with(MatchStats::default) += *stats;
+                }
+            }
+        }
+    }
+}
+
+// One row per entrant with its wins-losses-draws against the entrant of
+// each numbered column, then its totals and score
+impl fmt::Display for CrossTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name_width = self.names.iter().map(|n| n.len()).max().unwrap_or(0);
+        let cells: Vec<Vec<String>> = (0..self.names.len())
+            .map(|a| {
+                (0..self.names.len())
+                    .map(|b| match self.results[a][b] {
+                        _ if a == b => "x".to_string(),
+                        Some(stats) => format!("{}-{}-{}", stats.wins, stats.losses, stats.draws),
+                        None => "-".to_string(),
+                    })
+                    .collect()
+            })
+            .collect();
+        let cell_width = cells.iter().flatten().map(|c| c.len()).max().unwrap_or(1);
+
+        write!(f, "{:>3}  {:<name_width$}", "", "")?;
+        for b in 0..self.names.len() {
+            write!(f, "  {:>cell_width$}", b + 1)?;
+        }
+        writeln!(f, "  {:>9}  {:>6}", "Total", "Score")?;
+
+        for (a, name) in self.names.iter().enumerate() {
+            write!(f, "{:>3}  {:<name_width$}", a + 1, name)?;
+            for cell in &cells[a] {
+                write!(f, "  {:>cell_width$}", cell)?;
+            }
+            let total = self.total(a);
+            let record = format!("{}-{}-{}", total.wins, total.losses, total.draws);
+            let score = match total.games() {
+                0 => "-".to_string(),
+                _ => format!("{:.1}%", total.score() * 100.0),
+            };
+            writeln!(f, "  {:>9}  {:>6}", record, score)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,94 @@
+use crate::board::{Board, Stone};
+use crate::game::{Move, MoveError};
+use crate::rules::{KoRule, Rules};
+use std::time::Duration;
+
+// What a player gets to see when asked for a move: the position plus the
+// history and game state needed to play legally and manage time
+#[derive(Debug, Clone, Copy)]
+pub struct GameView<'a> {
+    pub board: &'a Board,
+    pub to_move: Stone,
+    pub board_history: &'a [u64], // Position hashes after every stone, ending with the current one
+    pub situation_history: &'a [u64], // Position plus side to move after every move, passes included
+    pub last_move: Option<Move>,
+    pub consecutive_passes: usize,
+    pub move_number: usize, // Moves played so far, passes included
+    pub rules: Rules,
+    pub time_left: Option<Duration>, // Remaining time of the side to move, if a clock is running
+}
+
+impl<'a> GameView<'a> {
+    // A bare position without history, e.g. inside playouts or benchmarks.
+    // Only the simple ko rule can be checked against it.
+    pub fn new(board: &'a Board, to_move: Stone) -> Self {
+        GameView {
+            board,
+            to_move,
+            board_history: &[],
+            situation_history: &[],
+            last_move: None,
+            consecutive_passes: 0,
+            move_number: 0,
+            rules: Rules::default(),
+            time_left: None,
+        }
+    }
+
+    pub fn komi(&self) -> f64 {
+        self.rules.komi
+    }
+
+    pub fn is_legal(&self, mv: Move) -> bool {
+        match mv {
+            Move::Place(x, y) => self.check_placement(x, y).is_ok(),
+            Move::Pass => true,
+        }
+    }
+
+    // Checks whether the side to move may play at (x, y)
+    pub fn check_placement(&self, x: usize, y: usize) -> Result<(), MoveError> {
+        self.check_point(x, y)?;
+
+        // Without a capture only a superko rule can see a repetition
+        if self.rules.ko == KoRule::Simple && !self.board.would_capture(x, y, self.to_move) {
+            return Ok(());
+        }
+
+        let mut next_board = self.board.clone_position();
+        next_board
+            .place_stone(x, y, self.to_move)
+            .map_err(|_| MoveError::Occupied)?;
+        if self.is_repetition(&next_board) {
+            return Err(MoveError::Ko);
+        }
+
+        Ok(())
+    }
+
+    // Every check except the ko rule, which needs the resulting position
+    pub fn check_point(&self, x: usize, y: usize) -> Result<(), MoveError> {
+        let size = self.board.size();
+        if x >= size || y >= size {
+            return Err(MoveError::OutOfBounds);
+        }
+        if self.board.get(x, y).is_some() {
+            return Err(MoveError::Occupied);
+        }
+        if !self.board.is_valid_move(x, y, self.to_move) {
+            return Err(MoveError::Suicide);
+        }
+        Ok(())
+    }
+
+    // Whether `next_board`, reached by a move of the side to move, repeats
+    // a position the ko rule forbids
+    pub fn is_repetition(&self, next_board: &Board) -> bool {
+        self.rules.ko.is_repetition(
+            self.board_history,
+            self.situation_history,
+            next_board.get_hash(),
+            next_board.get_situation_hash(self.to_move.opposite()),
+        )
+    }
+}
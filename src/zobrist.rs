@@ -1,37 +1,68 @@
 use rand::rngs::StdRng;
 use rand::{Rng, SeedableRng};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
 
+// Random keys for every (point, color) pair, plus keys for the side to move
+// and the ko point so search code can hash full situations. Tables are
+// immutable, so one table per board size is shared by every Board.
 #[derive(Debug, Clone)]
 pub struct ZobristTable {
-    black_table: Vec<Vec<u64>>,
-    white_table: Vec<Vec<u64>>,
+    board_size: usize,
+    stones: Vec<u64>, // Flat layout, black and white key of each point next to each other
+    ko_points: Vec<u64>,
+    side_to_move: u64,
 }
 
 impl ZobristTable {
     pub fn new(board_size: usize) -> Self {
         let mut rng = StdRng::seed_from_u64(42); // Fixed seed for consistency
 
-        let mut black_table = vec![vec![0u64; board_size]; board_size];
-        let mut white_table = vec![vec![0u64; board_size]; board_size];
-
-        for y in 0..board_size {
-            for x in 0..board_size {
-                black_table[y][x] = rng.gen();
-                white_table[y][x] = rng.gen();
-            }
+        let points = board_size * board_size;
+        let mut stones = Vec::with_capacity(points * 2);
+        for _ in 0..points * 2 {
+            stones.push(rng.gen());
         }
+        let ko_points = (0..points).map(|_| rng.gen()).collect();
 
         ZobristTable {
-            black_table,
-            white_table,
+            board_size,
+            stones,
+            ko_points,
+            side_to_move: rng.gen(),
         }
     }
 
+    // The shared table for `board_size`, built on first use
+    pub fn for_size(board_size: usize) -> &'static ZobristTable {
+        static TABLES: OnceLock<Mutex<HashMap<usize, &'static ZobristTable>>> = OnceLock::new();
+
+        let mut tables = TABLES
+            .get_or_init(|| Mutex::new(HashMap::new()))
+            .lock()
+            .unwrap();
+        tables
+            .entry(board_size)
+            .or_insert_with(|| Box::leak(Box::new(ZobristTable::new(board_size))))
+    }
+
+    #[inline(always)]
     pub fn get_stone_hash(&self, x: usize, y: usize, is_black: bool) -> u64 {
+        let idx = (y * self.board_size + x) * 2;
         if is_black {
-            self.black_table[y][x]
+            self.stones[idx]
         } else {
-            self.white_table[y][x]
+            self.stones[idx + 1]
         }
     }
+
+    // XORed into a position hash when White is to move
+    pub fn side_to_move_hash(&self) -> u64 {
+        self.side_to_move
+    }
+
+    // XORed into a position hash when (x, y) is a ko point nobody may retake
+    pub fn ko_hash(&self, x: usize, y: usize) -> u64 {
+        self.ko_points[y * self.board_size + x]
+    }
 }